@@ -0,0 +1,734 @@
+//! ARINC 653 APEX communication services: queuing ports between
+//! partitions, and buffers, blackboards and events within one.
+//!
+//! Each service mirrors its APEX counterpart. `Ok` stands for `NO_ERROR`;
+//! every other outcome is reported as a [`ReturnCode`] carrying the numeric
+//! value used by the C binding.
+
+use std::string::{String, ToString};
+use std::sync::Arc;
+use std::time::Duration;
+use std::vec::Vec;
+
+use spin::Mutex;
+
+use crate::config::SystemConfig;
+pub use crate::direction::PortDirection;
+use crate::error::PortError;
+use crate::handle::DynamicPortHandle;
+use crate::intra::{Blackboard, Buffer, EventFlag};
+use crate::port::Received;
+pub use crate::wait::QueuingDiscipline;
+
+pub type SystemTime = i64;
+pub type MessageSize = usize;
+pub type MessageRange = usize;
+pub type QueuingPortId = usize;
+
+pub const INFINITE_TIME_VALUE: SystemTime = -1;
+pub const MAX_NAME_LENGTH: usize = 30;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[repr(u32)]
+pub enum ReturnCode {
+    NoError = 0,
+    NoAction = 1,
+    NotAvailable = 2,
+    InvalidParam = 3,
+    InvalidConfig = 4,
+    InvalidMode = 5,
+    TimedOut = 6,
+}
+
+impl<T> From<Result<T, ReturnCode>> for ReturnCode {
+    fn from(result: Result<T, ReturnCode>) -> Self {
+        match result {
+            Ok(_) => ReturnCode::NoError,
+            Err(code) => code,
+        }
+    }
+}
+
+impl From<PortError> for ReturnCode {
+    fn from(error: PortError) -> Self {
+        match error {
+            PortError::Full | PortError::Empty | PortError::OwnerDied => ReturnCode::NotAvailable,
+            PortError::Timeout => ReturnCode::TimedOut,
+            PortError::WrongDirection => ReturnCode::InvalidMode,
+            PortError::Oversize | PortError::TooManyWaiters => ReturnCode::InvalidParam,
+            PortError::LayoutMismatch
+            | PortError::ShmCreate
+            | PortError::ShmOpen
+            | PortError::AlreadyExists
+            | PortError::Undeclared
+            | PortError::Multicast
+            | PortError::TooManyAttachers => ReturnCode::InvalidConfig,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct QueuingPortStatus {
+    pub nb_message: MessageRange,
+    pub max_nb_message: MessageRange,
+    pub max_message_size: MessageSize,
+    pub port_direction: PortDirection,
+    pub waiting_processes: usize,
+    /// A message was turned away since the destination last received.
+    pub overflow: bool,
+}
+
+// === Port Table ===
+
+struct ApexPort {
+    name: String,
+    direction: PortDirection,
+    handle: Arc<DynamicPortHandle>,
+}
+
+// Ports created by this partition; a port's id is its position plus one.
+static PORTS: Mutex<Vec<ApexPort>> = Mutex::new(Vec::new());
+
+// The system configuration and the partition this process runs, once loaded.
+static CONFIGURATION: Mutex<Option<(SystemConfig, String)>> = Mutex::new(None);
+
+/// Restricts this process to the ports `partition` declares in `config`.
+/// From then on CREATE_QUEUING_PORT only accepts declared ports, with their
+/// declared attributes, and attaches to the segments the port manager created
+/// for them under the configuration's recovery policy.
+pub fn configure_partition(config: SystemConfig, partition: &str) -> Result<(), ReturnCode> {
+    if config.partition(partition).is_none() {
+        return Err(ReturnCode::InvalidConfig);
+    }
+
+    *CONFIGURATION.lock() = Some((config, partition.to_string()));
+    Ok(())
+}
+
+fn port(id: QueuingPortId) -> Result<(PortDirection, Arc<DynamicPortHandle>), ReturnCode> {
+    let ports = PORTS.lock();
+    let port = id
+        .checked_sub(1)
+        .and_then(|index| ports.get(index))
+        .ok_or(ReturnCode::InvalidParam)?;
+    Ok((port.direction, Arc::clone(&port.handle)))
+}
+
+// Converts an APEX time-out into the wait the port should perform: none for
+// zero, a bounded one for a positive value, or forever for INFINITE_TIME_VALUE.
+fn wait_time(time_out: SystemTime) -> Result<Option<Option<Duration>>, ReturnCode> {
+    match time_out {
+        0 => Ok(None),
+        INFINITE_TIME_VALUE => Ok(Some(None)),
+        nanos if nanos > 0 => Ok(Some(Some(Duration::from_nanos(nanos as u64)))),
+        _ => Err(ReturnCode::InvalidParam),
+    }
+}
+
+// === Services ===
+
+/// CREATE_QUEUING_PORT. Both ends of a channel create the port under the same
+/// name; the second one attaches to the segment made by the first and must
+/// ask for the same geometry.
+pub fn create_queuing_port(
+    name: &str,
+    max_message_size: MessageSize,
+    max_nb_message: MessageRange,
+    direction: PortDirection,
+    discipline: QueuingDiscipline,
+) -> Result<QueuingPortId, ReturnCode> {
+    if name.is_empty() || name.len() > MAX_NAME_LENGTH {
+        return Err(ReturnCode::InvalidConfig);
+    }
+    if max_message_size == 0 || max_nb_message == 0 {
+        return Err(ReturnCode::InvalidConfig);
+    }
+
+    let mut ports = PORTS.lock();
+    if ports.iter().any(|port| port.name == name) {
+        return Err(ReturnCode::NoAction);
+    }
+
+    let handle = match &*CONFIGURATION.lock() {
+        Some((config, partition)) => {
+            let port = config
+                .port(partition, name)
+                .ok_or(ReturnCode::InvalidConfig)?;
+            if (
+                port.max_message_size,
+                port.max_nb_message,
+                port.direction,
+                port.discipline,
+            ) != (max_message_size, max_nb_message, direction, discipline)
+            {
+                return Err(ReturnCode::InvalidConfig);
+            }
+            config.open_port(partition, name)?
+        }
+        None => {
+            let handle = DynamicPortHandle::open_or_create(name, max_nb_message, max_message_size)?;
+            handle.set_discipline(direction, discipline);
+            handle
+        }
+    };
+
+    ports.push(ApexPort {
+        name: name.to_string(),
+        direction,
+        handle: Arc::new(handle),
+    });
+    Ok(ports.len())
+}
+
+/// SEND_QUEUING_MESSAGE.
+pub fn send_queuing_message(
+    id: QueuingPortId,
+    message: &[u8],
+    time_out: SystemTime,
+) -> Result<(), ReturnCode> {
+    let (direction, handle) = port(id)?;
+    if message.is_empty() || message.len() > handle.max_message_size() {
+        return Err(ReturnCode::InvalidParam);
+    }
+    if direction != PortDirection::Source {
+        return Err(ReturnCode::InvalidMode);
+    }
+
+    match wait_time(time_out)? {
+        None => handle.send(message)?,
+        Some(timeout) => handle.send_timeout(message, timeout)?,
+    }
+    Ok(())
+}
+
+/// RECEIVE_QUEUING_MESSAGE. `message` must be able to hold the port's
+/// maximum message size. The received length is returned together with the
+/// overflow condition, which the C binding reports as `INVALID_CONFIG`
+/// alongside the message.
+pub fn receive_queuing_message(
+    id: QueuingPortId,
+    time_out: SystemTime,
+    message: &mut [u8],
+) -> Result<Received, ReturnCode> {
+    let (direction, handle) = port(id)?;
+    if message.len() < handle.max_message_size() {
+        return Err(ReturnCode::InvalidParam);
+    }
+    if direction != PortDirection::Destination {
+        return Err(ReturnCode::InvalidMode);
+    }
+
+    let received = match wait_time(time_out)? {
+        None => handle.receive(message)?,
+        Some(timeout) => handle.receive_timeout(message, timeout)?,
+    };
+    Ok(received)
+}
+
+/// GET_QUEUING_PORT_ID.
+pub fn get_queuing_port_id(name: &str) -> Result<QueuingPortId, ReturnCode> {
+    PORTS
+        .lock()
+        .iter()
+        .position(|port| port.name == name)
+        .map(|index| index + 1)
+        .ok_or(ReturnCode::InvalidConfig)
+}
+
+/// GET_QUEUING_PORT_STATUS.
+pub fn get_queuing_port_status(id: QueuingPortId) -> Result<QueuingPortStatus, ReturnCode> {
+    let (direction, handle) = port(id)?;
+    Ok(QueuingPortStatus {
+        nb_message: handle.len(),
+        max_nb_message: handle.capacity(),
+        max_message_size: handle.max_message_size(),
+        port_direction: direction,
+        waiting_processes: handle.waiting(direction),
+        overflow: handle.overflowed(),
+    })
+}
+
+/// CLEAR_QUEUING_PORT. Only a destination port may discard its messages.
+pub fn clear_queuing_port(id: QueuingPortId) -> Result<(), ReturnCode> {
+    let (direction, handle) = port(id)?;
+    if direction != PortDirection::Destination {
+        return Err(ReturnCode::InvalidMode);
+    }
+
+    handle.clear();
+    Ok(())
+}
+
+/// The queuing discipline the port was created with.
+pub fn get_queuing_port_discipline(id: QueuingPortId) -> Result<QueuingDiscipline, ReturnCode> {
+    let (direction, handle) = port(id)?;
+    Ok(handle.discipline(direction))
+}
+
+// === Intra-Partition Objects ===
+
+pub type BufferId = usize;
+pub type BlackboardId = usize;
+pub type EventId = usize;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct BufferStatus {
+    pub nb_message: MessageRange,
+    pub max_nb_message: MessageRange,
+    pub max_message_size: MessageSize,
+    pub waiting_processes: usize,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct BlackboardStatus {
+    pub empty_indicator: bool,
+    pub max_message_size: MessageSize,
+    pub waiting_processes: usize,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[repr(u32)]
+pub enum EventState {
+    Down = 0,
+    Up = 1,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct EventStatus {
+    pub event_state: EventState,
+    pub waiting_processes: usize,
+}
+
+struct Named<T> {
+    name: String,
+    object: Arc<T>,
+}
+
+type Table<T> = Mutex<Vec<Named<T>>>;
+
+// Objects created by this partition, one table per kind; as for ports, an
+// object's id is its position in its table plus one.
+static BUFFERS: Table<Buffer> = Mutex::new(Vec::new());
+static BLACKBOARDS: Table<Blackboard> = Mutex::new(Vec::new());
+static EVENTS: Table<EventFlag> = Mutex::new(Vec::new());
+
+fn create_named<T>(
+    table: &Table<T>,
+    name: &str,
+    make: impl FnOnce() -> Result<T, ReturnCode>,
+) -> Result<usize, ReturnCode> {
+    if name.is_empty() || name.len() > MAX_NAME_LENGTH {
+        return Err(ReturnCode::InvalidConfig);
+    }
+
+    let mut table = table.lock();
+    if table.iter().any(|entry| entry.name == name) {
+        return Err(ReturnCode::NoAction);
+    }
+
+    table.push(Named {
+        name: name.to_string(),
+        object: Arc::new(make()?),
+    });
+    Ok(table.len())
+}
+
+fn named<T>(table: &Table<T>, id: usize) -> Result<Arc<T>, ReturnCode> {
+    let table = table.lock();
+    id.checked_sub(1)
+        .and_then(|index| table.get(index))
+        .map(|entry| Arc::clone(&entry.object))
+        .ok_or(ReturnCode::InvalidParam)
+}
+
+fn named_id<T>(table: &Table<T>, name: &str) -> Result<usize, ReturnCode> {
+    table
+        .lock()
+        .iter()
+        .position(|entry| entry.name == name)
+        .map(|index| index + 1)
+        .ok_or(ReturnCode::InvalidConfig)
+}
+
+/// CREATE_BUFFER.
+pub fn create_buffer(
+    name: &str,
+    max_message_size: MessageSize,
+    max_nb_message: MessageRange,
+    discipline: QueuingDiscipline,
+) -> Result<BufferId, ReturnCode> {
+    if max_message_size == 0 || max_nb_message == 0 {
+        return Err(ReturnCode::InvalidConfig);
+    }
+    create_named(&BUFFERS, name, || {
+        Ok(Buffer::new(max_nb_message, max_message_size, discipline)?)
+    })
+}
+
+/// SEND_BUFFER.
+pub fn send_buffer(id: BufferId, message: &[u8], time_out: SystemTime) -> Result<(), ReturnCode> {
+    let buffer = named(&BUFFERS, id)?;
+    if message.is_empty() || message.len() > buffer.max_message_size() {
+        return Err(ReturnCode::InvalidParam);
+    }
+
+    match wait_time(time_out)? {
+        None => buffer.send(message)?,
+        Some(timeout) => buffer.send_timeout(message, timeout)?,
+    }
+    Ok(())
+}
+
+/// RECEIVE_BUFFER. `message` must be able to hold the buffer's maximum
+/// message size; the received length is returned.
+pub fn receive_buffer(
+    id: BufferId,
+    time_out: SystemTime,
+    message: &mut [u8],
+) -> Result<MessageSize, ReturnCode> {
+    let buffer = named(&BUFFERS, id)?;
+    if message.len() < buffer.max_message_size() {
+        return Err(ReturnCode::InvalidParam);
+    }
+
+    let received = match wait_time(time_out)? {
+        None => buffer.receive(message)?,
+        Some(timeout) => buffer.receive_timeout(message, timeout)?,
+    };
+    Ok(received.len)
+}
+
+/// GET_BUFFER_ID.
+pub fn get_buffer_id(name: &str) -> Result<BufferId, ReturnCode> {
+    named_id(&BUFFERS, name)
+}
+
+/// GET_BUFFER_STATUS.
+pub fn get_buffer_status(id: BufferId) -> Result<BufferStatus, ReturnCode> {
+    let buffer = named(&BUFFERS, id)?;
+    Ok(BufferStatus {
+        nb_message: buffer.len(),
+        max_nb_message: buffer.capacity(),
+        max_message_size: buffer.max_message_size(),
+        waiting_processes: buffer.waiting(PortDirection::Source)
+            + buffer.waiting(PortDirection::Destination),
+    })
+}
+
+/// CREATE_BLACKBOARD.
+pub fn create_blackboard(
+    name: &str,
+    max_message_size: MessageSize,
+) -> Result<BlackboardId, ReturnCode> {
+    if max_message_size == 0 {
+        return Err(ReturnCode::InvalidConfig);
+    }
+    create_named(&BLACKBOARDS, name, || Ok(Blackboard::new(max_message_size)))
+}
+
+/// DISPLAY_BLACKBOARD.
+pub fn display_blackboard(id: BlackboardId, message: &[u8]) -> Result<(), ReturnCode> {
+    let blackboard = named(&BLACKBOARDS, id)?;
+    if message.is_empty() {
+        return Err(ReturnCode::InvalidParam);
+    }
+
+    blackboard.display(message)?;
+    Ok(())
+}
+
+/// READ_BLACKBOARD. `message` must be able to hold the blackboard's maximum
+/// message size; the displayed length is returned.
+pub fn read_blackboard(
+    id: BlackboardId,
+    time_out: SystemTime,
+    message: &mut [u8],
+) -> Result<MessageSize, ReturnCode> {
+    let blackboard = named(&BLACKBOARDS, id)?;
+    if message.len() < blackboard.max_message_size() {
+        return Err(ReturnCode::InvalidParam);
+    }
+
+    let len = match wait_time(time_out)? {
+        None => blackboard.read(message)?,
+        Some(timeout) => blackboard.read_timeout(message, timeout)?,
+    };
+    Ok(len)
+}
+
+/// CLEAR_BLACKBOARD.
+pub fn clear_blackboard(id: BlackboardId) -> Result<(), ReturnCode> {
+    named(&BLACKBOARDS, id)?.clear();
+    Ok(())
+}
+
+/// GET_BLACKBOARD_ID.
+pub fn get_blackboard_id(name: &str) -> Result<BlackboardId, ReturnCode> {
+    named_id(&BLACKBOARDS, name)
+}
+
+/// GET_BLACKBOARD_STATUS.
+pub fn get_blackboard_status(id: BlackboardId) -> Result<BlackboardStatus, ReturnCode> {
+    let blackboard = named(&BLACKBOARDS, id)?;
+    Ok(BlackboardStatus {
+        empty_indicator: blackboard.is_empty(),
+        max_message_size: blackboard.max_message_size(),
+        waiting_processes: blackboard.waiting(),
+    })
+}
+
+/// CREATE_EVENT. The event starts down.
+pub fn create_event(name: &str) -> Result<EventId, ReturnCode> {
+    create_named(&EVENTS, name, || Ok(EventFlag::new()))
+}
+
+/// SET_EVENT.
+pub fn set_event(id: EventId) -> Result<(), ReturnCode> {
+    named(&EVENTS, id)?.set();
+    Ok(())
+}
+
+/// RESET_EVENT.
+pub fn reset_event(id: EventId) -> Result<(), ReturnCode> {
+    named(&EVENTS, id)?.reset();
+    Ok(())
+}
+
+/// WAIT_EVENT.
+pub fn wait_event(id: EventId, time_out: SystemTime) -> Result<(), ReturnCode> {
+    let event = named(&EVENTS, id)?;
+    match wait_time(time_out)? {
+        None if event.is_up() => Ok(()),
+        None => Err(ReturnCode::NotAvailable),
+        Some(timeout) => Ok(event.wait_timeout(timeout)?),
+    }
+}
+
+/// GET_EVENT_ID.
+pub fn get_event_id(name: &str) -> Result<EventId, ReturnCode> {
+    named_id(&EVENTS, name)
+}
+
+/// GET_EVENT_STATUS.
+pub fn get_event_status(id: EventId) -> Result<EventStatus, ReturnCode> {
+    let event = named(&EVENTS, id)?;
+    Ok(EventStatus {
+        event_state: if event.is_up() {
+            EventState::Up
+        } else {
+            EventState::Down
+        },
+        waiting_processes: event.waiting(),
+    })
+}
+
+// === Tests ===
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::shared::unlink;
+
+    #[test]
+    fn test_apex_send_receive() {
+        let _ = unlink("test_apex_basic");
+        let source = create_queuing_port(
+            "test_apex_basic",
+            16,
+            2,
+            PortDirection::Source,
+            QueuingDiscipline::Fifo,
+        )
+        .unwrap();
+        let destination = create_queuing_port(
+            "test_apex_basic",
+            16,
+            2,
+            PortDirection::Destination,
+            QueuingDiscipline::Fifo,
+        );
+        assert_eq!(destination, Err(ReturnCode::NoAction));
+        assert_eq!(get_queuing_port_id("test_apex_basic"), Ok(source));
+
+        send_queuing_message(source, b"one", 0).unwrap();
+        send_queuing_message(source, b"two", 0).unwrap();
+        assert_eq!(
+            send_queuing_message(source, b"three", 0),
+            Err(ReturnCode::NotAvailable)
+        );
+        assert_eq!(
+            send_queuing_message(source, b"three", 1_000_000),
+            Err(ReturnCode::TimedOut)
+        );
+        assert_eq!(
+            send_queuing_message(source, &[0; 17], 0),
+            Err(ReturnCode::InvalidParam)
+        );
+
+        let status = get_queuing_port_status(source).unwrap();
+        assert_eq!(status.nb_message, 2);
+        assert_eq!(status.max_nb_message, 2);
+        assert_eq!(status.max_message_size, 16);
+        assert_eq!(status.port_direction, PortDirection::Source);
+        assert!(status.overflow);
+
+        let mut buf = [0u8; 16];
+        assert_eq!(
+            receive_queuing_message(source, 0, &mut buf),
+            Err(ReturnCode::InvalidMode)
+        );
+        assert_eq!(clear_queuing_port(source), Err(ReturnCode::InvalidMode));
+
+        // The destination partition drains the channel.
+        DynamicPortHandle::open("test_apex_basic").unwrap().clear();
+        assert_eq!(get_queuing_port_status(source).unwrap().nb_message, 0);
+
+        // Ports live as long as the partition; remove the segment.
+        unlink("test_apex_basic").unwrap();
+    }
+
+    #[test]
+    fn test_apex_invalid_requests() {
+        assert_eq!(
+            create_queuing_port("", 8, 4, PortDirection::Source, QueuingDiscipline::Fifo),
+            Err(ReturnCode::InvalidConfig)
+        );
+        assert_eq!(
+            create_queuing_port(
+                "test_apex_zero",
+                0,
+                4,
+                PortDirection::Source,
+                QueuingDiscipline::Fifo
+            ),
+            Err(ReturnCode::InvalidConfig)
+        );
+        assert_eq!(
+            get_queuing_port_id("test_apex_missing"),
+            Err(ReturnCode::InvalidConfig)
+        );
+        assert_eq!(
+            get_queuing_port_status(0).map(|_| ()),
+            Err(ReturnCode::InvalidParam)
+        );
+        assert_eq!(
+            ReturnCode::from(send_queuing_message(usize::MAX, b"x", 0)) as u32,
+            3
+        );
+    }
+
+    #[test]
+    fn test_apex_receive_and_clear() {
+        let _ = unlink("test_apex_receive");
+        let port = create_queuing_port(
+            "test_apex_receive",
+            8,
+            4,
+            PortDirection::Destination,
+            QueuingDiscipline::Priority,
+        )
+        .unwrap();
+        assert_eq!(
+            get_queuing_port_discipline(port),
+            Ok(QueuingDiscipline::Priority)
+        );
+
+        // The source partition attaches to the same segment.
+        let source = DynamicPortHandle::open("test_apex_receive").unwrap();
+
+        let mut buf = [0u8; 8];
+        assert_eq!(
+            receive_queuing_message(port, 0, &mut buf),
+            Err(ReturnCode::NotAvailable)
+        );
+
+        source.send(b"hello").unwrap();
+        assert_eq!(
+            receive_queuing_message(port, 0, &mut buf[..4]),
+            Err(ReturnCode::InvalidParam)
+        );
+        assert_eq!(
+            receive_queuing_message(port, INFINITE_TIME_VALUE, &mut buf),
+            Ok(Received {
+                len: 5,
+                overflow: false
+            })
+        );
+        assert_eq!(&buf[..5], b"hello");
+
+        source.send(b"stale").unwrap();
+        assert_eq!(get_queuing_port_status(port).unwrap().nb_message, 1);
+        assert_eq!(clear_queuing_port(port), Ok(()));
+        assert_eq!(
+            receive_queuing_message(port, 1_000_000, &mut buf),
+            Err(ReturnCode::TimedOut)
+        );
+        unlink("test_apex_receive").unwrap();
+    }
+
+    #[test]
+    fn test_apex_buffers() {
+        let buffer = create_buffer("test_apex_buffer", 8, 2, QueuingDiscipline::Priority).unwrap();
+        assert_eq!(
+            create_buffer("test_apex_buffer", 8, 2, QueuingDiscipline::Fifo),
+            Err(ReturnCode::NoAction)
+        );
+        assert_eq!(get_buffer_id("test_apex_buffer"), Ok(buffer));
+
+        send_buffer(buffer, b"one", 0).unwrap();
+        send_buffer(buffer, b"two", INFINITE_TIME_VALUE).unwrap();
+        assert_eq!(
+            send_buffer(buffer, b"three", 1_000_000),
+            Err(ReturnCode::TimedOut)
+        );
+
+        let status = get_buffer_status(buffer).unwrap();
+        assert_eq!((status.nb_message, status.max_nb_message), (2, 2));
+        assert_eq!(
+            create_buffer("test_apex_huge", 8, usize::MAX / 4, QueuingDiscipline::Fifo),
+            Err(ReturnCode::InvalidConfig)
+        );
+
+        let mut buf = [0u8; 8];
+        assert_eq!(receive_buffer(buffer, 0, &mut buf), Ok(3));
+        assert_eq!(&buf[..3], b"one");
+        assert_eq!(receive_buffer(buffer, 0, &mut buf), Ok(3));
+        assert_eq!(
+            receive_buffer(buffer, 0, &mut buf),
+            Err(ReturnCode::NotAvailable)
+        );
+    }
+
+    #[test]
+    fn test_apex_blackboards_and_events() {
+        let blackboard = create_blackboard("test_apex_blackboard", 8).unwrap();
+        let mut buf = [0u8; 8];
+
+        assert!(get_blackboard_status(blackboard).unwrap().empty_indicator);
+        assert_eq!(
+            read_blackboard(blackboard, 0, &mut buf),
+            Err(ReturnCode::NotAvailable)
+        );
+        display_blackboard(blackboard, b"mode").unwrap();
+        assert_eq!(
+            read_blackboard(blackboard, INFINITE_TIME_VALUE, &mut buf),
+            Ok(4)
+        );
+        assert_eq!(read_blackboard(blackboard, 0, &mut buf), Ok(4));
+        clear_blackboard(blackboard).unwrap();
+        assert_eq!(
+            read_blackboard(blackboard, 1_000_000, &mut buf),
+            Err(ReturnCode::TimedOut)
+        );
+
+        let event = create_event("test_apex_event").unwrap();
+        assert_eq!(get_event_id("test_apex_event"), Ok(event));
+        assert_eq!(wait_event(event, 0), Err(ReturnCode::NotAvailable));
+        set_event(event).unwrap();
+        assert_eq!(get_event_status(event).unwrap().event_state, EventState::Up);
+        assert_eq!(wait_event(event, INFINITE_TIME_VALUE), Ok(()));
+        reset_event(event).unwrap();
+        assert_eq!(wait_event(event, 1_000_000), Err(ReturnCode::TimedOut));
+        assert_eq!(set_event(event + 1), Err(ReturnCode::InvalidParam));
+    }
+}
@@ -0,0 +1,314 @@
+//! Build-script support: turns a system configuration into a Rust module with
+//! one typed constructor per declared port, so a misspelled port name fails
+//! to compile instead of creating a stray segment at run time.
+//!
+//! In `build.rs`:
+//!
+//! ```no_run
+//! master_project_queuing_port::codegen::write_ports("system.toml", "ports.rs").unwrap();
+//! ```
+//!
+//! and in the crate:
+//!
+//! ```ignore
+//! mod ports {
+//!     include!(concat!(env!("OUT_DIR"), "/ports.rs"));
+//! }
+//!
+//! let source = ports::navigation::position_out::open()?;
+//! ```
+//!
+//! Each generated module carries the port's geometry as constants, and
+//! `open` returns a port typed by them, such as
+//! `SourcePort<Message<MAX_MESSAGE_SIZE>, MAX_NB_MESSAGE>`. The port manager
+//! creates every segment laid out as that typed port, so opening checks the
+//! segment against the declared geometry. A multicast source returns a
+//! [`MulticastSourcePort`](crate::MulticastSourcePort) writing to each of
+//! its destinations.
+
+use std::collections::BTreeSet;
+use std::env;
+use std::fmt::Write;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::string::String;
+use std::vec::Vec;
+
+use crate::config::{ConfigError, PortConfig, SystemConfig};
+use crate::direction::PortDirection;
+use crate::wait::QueuingDiscipline;
+
+// Keywords, strict and reserved, that are valid identifiers once raw.
+const KEYWORDS: &[&str] = &[
+    "abstract", "as", "async", "await", "become", "box", "break", "const", "continue", "do", "dyn",
+    "else", "enum", "extern", "false", "final", "fn", "for", "gen", "if", "impl", "in", "let",
+    "loop", "macro", "match", "mod", "move", "mut", "override", "priv", "pub", "ref", "return",
+    "static", "struct", "trait", "true", "try", "type", "typeof", "unsafe", "unsized", "use",
+    "virtual", "where", "while", "yield",
+];
+
+// Names that cannot be raw identifiers.
+const NOT_RAW: &[&str] = &["_", "crate", "self", "Self", "super"];
+
+// Turns a configured name into a Rust identifier.
+fn identifier(name: &str) -> String {
+    let mut ident: String = name
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+        .collect();
+    if ident.starts_with(|c: char| c.is_ascii_digit()) {
+        ident.insert(0, '_');
+    }
+    if KEYWORDS.contains(&ident.as_str()) {
+        ident.insert_str(0, "r#");
+    } else if NOT_RAW.contains(&ident.as_str()) {
+        ident.push('_');
+    }
+    ident
+}
+
+fn unique(seen: &mut BTreeSet<String>, ident: String, name: &str) -> Result<String, ConfigError> {
+    if seen.insert(ident.clone()) {
+        Ok(ident)
+    } else {
+        Err(ConfigError::Invalid(format!(
+            "{name:?} collides with another name once made a Rust identifier"
+        )))
+    }
+}
+
+fn write_port(out: &mut String, config: &SystemConfig, port: &PortConfig, ident: &str) {
+    let multicast = config.is_multicast(&port.name);
+    let (kind, port_type) = match port.direction {
+        PortDirection::Source if multicast => ("multicast source", "MulticastSourcePort"),
+        PortDirection::Source => (
+            "source",
+            "SourcePort<qp::Message<MAX_MESSAGE_SIZE>, MAX_NB_MESSAGE>",
+        ),
+        PortDirection::Destination => (
+            "destination",
+            "DestinationPort<qp::Message<MAX_MESSAGE_SIZE>, MAX_NB_MESSAGE>",
+        ),
+    };
+    let discipline = match port.discipline {
+        QueuingDiscipline::Fifo => "Fifo",
+        QueuingDiscipline::Priority => "Priority",
+    };
+
+    let _ = writeln!(
+        out,
+        "    /// Port {:?}: {kind} of up to {} messages of at most {} bytes.",
+        port.name, port.max_nb_message, port.max_message_size
+    );
+    let _ = writeln!(out, "    #[allow(non_snake_case)]");
+    let _ = writeln!(out, "    pub mod {ident} {{");
+    let _ = writeln!(out, "        use ::master_project_queuing_port as qp;\n");
+    let _ = writeln!(
+        out,
+        "        pub const MAX_MESSAGE_SIZE: usize = {};",
+        port.max_message_size
+    );
+    let _ = writeln!(
+        out,
+        "        pub const MAX_NB_MESSAGE: usize = {};",
+        port.max_nb_message
+    );
+    let _ = writeln!(
+        out,
+        "        pub const RECOVERY: qp::RecoveryPolicy = qp::RecoveryPolicy::{:?};",
+        config.recovery
+    );
+    let _ = writeln!(out, "        pub type Port = qp::{port_type};");
+
+    // A multicast source writes to the segment of each of its destinations.
+    if multicast {
+        let segments: Vec<&str> = config
+            .destinations(&port.name)
+            .map(|destination| config.segment_name(destination))
+            .collect();
+        let _ = writeln!(
+            out,
+            "        pub const SEGMENTS: &[&str] = &{segments:?};\n"
+        );
+        let _ = writeln!(
+            out,
+            "        pub fn open() -> Result<Port, qp::PortError> {{"
+        );
+        let _ = writeln!(
+            out,
+            "            let destinations = SEGMENTS.iter().map(|segment| {{"
+        );
+        let _ = writeln!(
+            out,
+            "                let handle = qp::DynamicPortHandle::open_message_port(segment, MAX_NB_MESSAGE, MAX_MESSAGE_SIZE, RECOVERY)?;"
+        );
+        let _ = writeln!(
+            out,
+            "                Ok(qp::DynamicSourcePort::with_discipline(handle, qp::QueuingDiscipline::{discipline}))"
+        );
+        let _ = writeln!(
+            out,
+            "            }}).collect::<Result<Vec<_>, qp::PortError>>()?;"
+        );
+        let _ = writeln!(
+            out,
+            "            qp::MulticastSourcePort::new(destinations)"
+        );
+    } else {
+        let _ = writeln!(
+            out,
+            "        pub const SEGMENT: &str = {:?};\n",
+            config.segment_name(&port.name)
+        );
+        let _ = writeln!(
+            out,
+            "        pub fn open() -> Result<Port, qp::PortError> {{"
+        );
+        let _ = writeln!(
+            out,
+            "            let handle = qp::PortHandle::open_with_policy(SEGMENT, RECOVERY)?;"
+        );
+        let _ = writeln!(
+            out,
+            "            Ok(Port::with_discipline(handle, qp::QueuingDiscipline::{discipline}))"
+        );
+    }
+    let _ = writeln!(out, "        }}");
+    let _ = writeln!(out, "    }}");
+}
+
+/// Generates the ports module for `config`: one module per partition holding
+/// one module per port, each with its geometry as constants and an `open`
+/// function returning the port typed by its direction.
+pub fn generate(config: &SystemConfig) -> Result<String, ConfigError> {
+    config.validate()?;
+
+    let mut out = String::from("// Generated from the system configuration. Do not edit.\n");
+    let mut partitions = BTreeSet::new();
+    for partition in &config.partitions {
+        let ident = unique(
+            &mut partitions,
+            identifier(&partition.name),
+            &partition.name,
+        )?;
+        let _ = writeln!(out, "\n/// Ports of partition {:?}.", partition.name);
+        let _ = writeln!(out, "#[allow(non_snake_case)]");
+        let _ = writeln!(out, "pub mod {ident} {{");
+
+        let mut ports = BTreeSet::new();
+        for (index, port) in partition.ports.iter().enumerate() {
+            let ident = unique(&mut ports, identifier(&port.name), &port.name)?;
+            if index > 0 {
+                out.push('\n');
+            }
+            write_port(&mut out, config, port, &ident);
+        }
+        out.push_str("}\n");
+    }
+    Ok(out)
+}
+
+/// For build scripts: generates the ports module from the configuration at
+/// `config_path` into `file_name` under `OUT_DIR`, and asks Cargo to rerun the
+/// build script when the configuration changes. Returns the written path.
+pub fn write_ports(config_path: impl AsRef<Path>, file_name: &str) -> Result<PathBuf, ConfigError> {
+    let config_path = config_path.as_ref();
+    println!("cargo:rerun-if-changed={}", config_path.display());
+
+    let config = SystemConfig::load(config_path)?;
+    let out_dir = env::var_os("OUT_DIR").ok_or_else(|| {
+        ConfigError::Invalid(String::from("OUT_DIR is not set; call this from build.rs"))
+    })?;
+
+    let path = Path::new(&out_dir).join(file_name);
+    fs::write(&path, generate(&config)?).map_err(ConfigError::Read)?;
+    Ok(path)
+}
+
+// === Tests ===
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_generated_ports() {
+        let config = SystemConfig::from_toml(
+            r#"
+            [[partition]]
+            name = "flight-control"
+
+            [[partition.port]]
+            name = "type"
+            direction = "source"
+            max_message_size = 32
+            max_nb_message = 4
+
+            [[partition]]
+            name = "display"
+
+            [[partition.port]]
+            name = "1st_in"
+            direction = "destination"
+            max_message_size = 32
+            max_nb_message = 4
+            discipline = "priority"
+
+            [[channel]]
+            source = "type"
+            destination = "1st_in"
+            "#,
+        )
+        .unwrap();
+
+        let code = generate(&config).unwrap();
+        assert!(code.contains("pub mod flight_control {"));
+        assert!(code.contains("pub mod r#type {"));
+        assert!(code.contains("pub mod _1st_in {"));
+        assert!(code.contains(
+            "pub type Port = qp::DestinationPort<qp::Message<MAX_MESSAGE_SIZE>, MAX_NB_MESSAGE>;"
+        ));
+        assert!(code.contains("pub fn open() -> Result<Port, qp::PortError>"));
+        assert!(code.contains("qp::QueuingDiscipline::Priority"));
+        assert!(code.contains("qp::RecoveryPolicy::Keep"));
+        assert_eq!(
+            code.matches("pub const SEGMENT: &str = \"type\";").count(),
+            2
+        );
+    }
+
+    #[test]
+    fn test_identifiers() {
+        assert_eq!(identifier("position-out"), "position_out");
+        assert_eq!(identifier("1st"), "_1st");
+        assert_eq!(identifier("box"), "r#box");
+        assert_eq!(identifier("self"), "self_");
+        assert_eq!(identifier("Self"), "Self_");
+        assert_eq!(identifier("_"), "__");
+    }
+
+    #[test]
+    fn test_colliding_identifiers() {
+        let config = SystemConfig::from_toml(
+            r#"
+            [[partition]]
+            name = "p"
+
+            [[partition.port]]
+            name = "a-b"
+            direction = "source"
+            max_message_size = 8
+            max_nb_message = 1
+
+            [[partition.port]]
+            name = "a_b"
+            direction = "source"
+            max_message_size = 8
+            max_nb_message = 1
+            "#,
+        )
+        .unwrap();
+
+        assert!(matches!(generate(&config), Err(ConfigError::Invalid(_))));
+    }
+}
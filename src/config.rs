@@ -0,0 +1,846 @@
+//! System configuration: the partitions, the ports each one owns and the
+//! channels connecting source ports to destination ports.
+//!
+//! A configuration is read from TOML:
+//!
+//! ```toml
+//! [[partition]]
+//! name = "navigation"
+//!
+//! [[partition.port]]
+//! name = "position_out"
+//! direction = "source"
+//! max_message_size = 64
+//! max_nb_message = 8
+//!
+//! [[partition]]
+//! name = "display"
+//!
+//! [[partition.port]]
+//! name = "position_in"
+//! direction = "destination"
+//! max_message_size = 64
+//! max_nb_message = 8
+//! discipline = "priority"
+//!
+//! [[channel]]
+//! source = "position_out"
+//! destination = "position_in"
+//! ```
+//!
+//! An optional top-level `recovery = "keep" | "reset" | "refuse"` sets what
+//! opening a port does after a process using it died; see
+//! [`RecoveryPolicy`].
+//!
+//! A source port named by several channels is multicast: each of its
+//! destinations keeps its own queue, in a segment named after the
+//! destination, and the source writes to all of them.
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
+use std::fs;
+use std::io;
+use std::ops::Range;
+use std::path::Path;
+use std::string::{String, ToString};
+use std::vec::Vec;
+
+use serde::Deserialize;
+use toml::Spanned;
+
+use crate::apex::MAX_NAME_LENGTH;
+use crate::direction::PortDirection;
+use crate::dynamic::DynamicQueuingPort;
+use crate::error::PortError;
+use crate::handle::DynamicPortHandle;
+use crate::header::{RecoveryPolicy, HEADER_SIZE};
+use crate::multicast::MulticastSourcePort;
+use crate::wait::QueuingDiscipline;
+
+/// Largest shared-memory segment a single port may need.
+pub const MAX_SEGMENT_SIZE: usize = 16 << 20;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SystemConfig {
+    pub partitions: Vec<PartitionConfig>,
+    pub channels: Vec<ChannelConfig>,
+    /// What opening a port does after a process using it died.
+    pub recovery: RecoveryPolicy,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PartitionConfig {
+    pub name: String,
+    pub ports: Vec<PortConfig>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PortConfig {
+    pub name: String,
+    pub direction: PortDirection,
+    pub max_message_size: usize,
+    pub max_nb_message: usize,
+    pub discipline: QueuingDiscipline,
+}
+
+/// Connects the source port `source` to the destination port `destination`.
+/// A source may feed several destinations; a destination has one source.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ChannelConfig {
+    pub source: String,
+    pub destination: String,
+}
+
+#[derive(Debug)]
+pub enum ConfigError {
+    Read(io::Error),
+    Parse(toml::de::Error),
+    Invalid(String),
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConfigError::Read(error) => write!(f, "cannot read configuration: {error}"),
+            ConfigError::Parse(error) => write!(f, "cannot parse configuration: {error}"),
+            ConfigError::Invalid(reason) => write!(f, "invalid configuration: {reason}"),
+        }
+    }
+}
+
+impl std::error::Error for ConfigError {}
+
+impl SystemConfig {
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, ConfigError> {
+        let text = fs::read_to_string(path).map_err(ConfigError::Read)?;
+        Self::from_toml(&text)
+    }
+
+    pub fn from_toml(text: &str) -> Result<Self, ConfigError> {
+        let raw: RawSystem = toml::from_str(text).map_err(ConfigError::Parse)?;
+        first_error(check(&raw))?;
+        Ok(raw.into())
+    }
+
+    /// Checks that names are unique, sizes are usable and every channel joins
+    /// a declared source to a declared destination of the same geometry.
+    /// Warnings do not make a configuration invalid.
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        first_error(check(&RawSystem::from(self)))
+    }
+
+    pub fn partition(&self, name: &str) -> Option<&PartitionConfig> {
+        self.partitions
+            .iter()
+            .find(|partition| partition.name == name)
+    }
+
+    /// The port `name` as declared by `partition`.
+    pub fn port(&self, partition: &str, name: &str) -> Option<&PortConfig> {
+        self.partition(partition)?
+            .ports
+            .iter()
+            .find(|port| port.name == name)
+    }
+
+    /// The destination ports fed by the source port `source`.
+    pub fn destinations<'a>(&'a self, source: &'a str) -> impl Iterator<Item = &'a str> + 'a {
+        self.channels
+            .iter()
+            .filter(move |channel| channel.source == source)
+            .map(|channel| channel.destination.as_str())
+    }
+
+    /// Whether the source port `name` feeds more than one destination.
+    pub fn is_multicast(&self, name: &str) -> bool {
+        self.destinations(name).nth(1).is_some()
+    }
+
+    /// Name of the shared-memory segment behind port `name`: both ends of a
+    /// channel share the segment named after its source port, except that
+    /// each destination of a multicast source has a segment of its own.
+    pub fn segment_name<'a>(&'a self, name: &'a str) -> &'a str {
+        match self
+            .channels
+            .iter()
+            .find(|channel| channel.destination == name)
+        {
+            Some(channel) if self.is_multicast(&channel.source) => name,
+            Some(channel) => &channel.source,
+            None => name,
+        }
+    }
+
+    /// Creates the segment behind every declared port, each a message port of
+    /// its declared geometry with the disciplines of both its ends, keyed by
+    /// segment name. A partition may open it with the typed
+    /// `PortHandle<Message<max_message_size>, max_nb_message>` as well. This is the port manager's job: partitions only open
+    /// ports that already exist. Fails if any segment already exists or a
+    /// channel names an undeclared port, and the segments are removed again
+    /// when the handles are dropped.
+    pub fn create_ports(&self) -> Result<BTreeMap<String, DynamicPortHandle>, PortError> {
+        let mut segments = BTreeMap::new();
+        for port in self
+            .partitions
+            .iter()
+            .flat_map(|partition| &partition.ports)
+        {
+            if self.is_multicast(&port.name) {
+                continue;
+            }
+            let segment = self.segment_name(&port.name);
+            if !segments.contains_key(segment) {
+                let handle = DynamicPortHandle::create_message_port(
+                    segment,
+                    port.max_nb_message,
+                    port.max_message_size,
+                    self.recovery,
+                )?;
+                segments.insert(segment.to_string(), handle);
+            }
+            segments[segment].set_discipline(port.direction, port.discipline);
+        }
+
+        // A multicast source writes to the segments of its destinations.
+        for port in self
+            .partitions
+            .iter()
+            .flat_map(|partition| &partition.ports)
+        {
+            if self.is_multicast(&port.name) {
+                for destination in self.destinations(&port.name) {
+                    segments
+                        .get(self.segment_name(destination))
+                        .ok_or(PortError::Undeclared)?
+                        .set_discipline(PortDirection::Source, port.discipline);
+                }
+            }
+        }
+        Ok(segments)
+    }
+
+    /// Opens the port `name` that `partition` declares, checking it against
+    /// the declared geometry and recovering it under the configuration's
+    /// policy. A multicast source has no single segment and is
+    /// opened with [`SystemConfig::open_multicast`] instead.
+    pub fn open_port(&self, partition: &str, name: &str) -> Result<DynamicPortHandle, PortError> {
+        let port = self.port(partition, name).ok_or(PortError::Undeclared)?;
+        if self.is_multicast(name) {
+            return Err(PortError::Multicast);
+        }
+        DynamicPortHandle::open_message_port(
+            self.segment_name(name),
+            port.max_nb_message,
+            port.max_message_size,
+            self.recovery,
+        )
+    }
+
+    /// Opens the queue of every destination the source port `name` of
+    /// `partition` feeds.
+    pub fn open_multicast(
+        &self,
+        partition: &str,
+        name: &str,
+    ) -> Result<MulticastSourcePort, PortError> {
+        let port = self.port(partition, name).ok_or(PortError::Undeclared)?;
+        if port.direction != PortDirection::Source {
+            return Err(PortError::WrongDirection);
+        }
+
+        let destinations = self
+            .destinations(name)
+            .map(|destination| {
+                DynamicPortHandle::open_message_port(
+                    self.segment_name(destination),
+                    port.max_nb_message,
+                    port.max_message_size,
+                    self.recovery,
+                )
+                .map(DynamicPortHandle::into_source)
+            })
+            .collect::<Result<Vec<_>, PortError>>()?;
+        MulticastSourcePort::new(destinations)
+    }
+}
+
+// === Diagnostics ===
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Severity {
+    Error,
+    Warning,
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Severity::Error => "error",
+            Severity::Warning => "warning",
+        })
+    }
+}
+
+/// One finding about a configuration file. `span` is the byte range in the
+/// file the finding points at.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    pub code: &'static str,
+    pub message: String,
+    pub span: Range<usize>,
+}
+
+impl Diagnostic {
+    /// The 1-based line and column of the start of `span` within `text`.
+    pub fn line_column(&self, text: &str) -> (usize, usize) {
+        let before = &text[..self.span.start.min(text.len())];
+        let line_start = before.rfind('\n').map_or(0, |newline| newline + 1);
+        (
+            before.matches('\n').count() + 1,
+            before[line_start..].chars().count() + 1,
+        )
+    }
+}
+
+/// Checks the TOML configuration in `text` and reports every problem found,
+/// not just the first.
+pub fn diagnose(text: &str) -> Vec<Diagnostic> {
+    match toml::from_str::<RawSystem>(text) {
+        Ok(raw) => check(&raw),
+        Err(error) => vec![Diagnostic {
+            severity: Severity::Error,
+            code: "parse",
+            message: error.message().to_string(),
+            span: error.span().unwrap_or(0..0),
+        }],
+    }
+}
+
+fn first_error(diagnostics: Vec<Diagnostic>) -> Result<(), ConfigError> {
+    match diagnostics
+        .into_iter()
+        .find(|diagnostic| diagnostic.severity == Severity::Error)
+    {
+        Some(diagnostic) => Err(ConfigError::Invalid(diagnostic.message)),
+        None => Ok(()),
+    }
+}
+
+// The configuration as written, with the position of every name so findings
+// can point back into the file.
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RawSystem {
+    #[serde(default)]
+    recovery: RecoveryPolicy,
+    #[serde(default, rename = "partition")]
+    partitions: Vec<RawPartition>,
+    #[serde(default, rename = "channel")]
+    channels: Vec<RawChannel>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RawPartition {
+    name: Spanned<String>,
+    #[serde(default, rename = "port")]
+    ports: Vec<RawPort>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RawPort {
+    name: Spanned<String>,
+    direction: PortDirection,
+    max_message_size: usize,
+    max_nb_message: usize,
+    #[serde(default)]
+    discipline: QueuingDiscipline,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RawChannel {
+    source: Spanned<String>,
+    destination: Spanned<String>,
+}
+
+fn unspanned(name: &str) -> Spanned<String> {
+    Spanned::new(0..0, name.to_string())
+}
+
+impl From<&SystemConfig> for RawSystem {
+    fn from(config: &SystemConfig) -> Self {
+        RawSystem {
+            recovery: config.recovery,
+            partitions: config
+                .partitions
+                .iter()
+                .map(|partition| RawPartition {
+                    name: unspanned(&partition.name),
+                    ports: partition
+                        .ports
+                        .iter()
+                        .map(|port| RawPort {
+                            name: unspanned(&port.name),
+                            direction: port.direction,
+                            max_message_size: port.max_message_size,
+                            max_nb_message: port.max_nb_message,
+                            discipline: port.discipline,
+                        })
+                        .collect(),
+                })
+                .collect(),
+            channels: config
+                .channels
+                .iter()
+                .map(|channel| RawChannel {
+                    source: unspanned(&channel.source),
+                    destination: unspanned(&channel.destination),
+                })
+                .collect(),
+        }
+    }
+}
+
+impl From<RawSystem> for SystemConfig {
+    fn from(raw: RawSystem) -> Self {
+        SystemConfig {
+            partitions: raw
+                .partitions
+                .into_iter()
+                .map(|partition| PartitionConfig {
+                    name: partition.name.into_inner(),
+                    ports: partition
+                        .ports
+                        .into_iter()
+                        .map(|port| PortConfig {
+                            name: port.name.into_inner(),
+                            direction: port.direction,
+                            max_message_size: port.max_message_size,
+                            max_nb_message: port.max_nb_message,
+                            discipline: port.discipline,
+                        })
+                        .collect(),
+                })
+                .collect(),
+            channels: raw
+                .channels
+                .into_iter()
+                .map(|channel| ChannelConfig {
+                    source: channel.source.into_inner(),
+                    destination: channel.destination.into_inner(),
+                })
+                .collect(),
+            recovery: raw.recovery,
+        }
+    }
+}
+
+fn direction_name(direction: PortDirection) -> &'static str {
+    match direction {
+        PortDirection::Source => "source",
+        PortDirection::Destination => "destination",
+    }
+}
+
+// Bytes of shared memory a port needs, or `None` if that overflows.
+fn segment_size(max_nb_message: usize, max_message_size: usize) -> Option<usize> {
+    DynamicQueuingPort::message_port_size(max_nb_message, max_message_size)?
+        .checked_add(HEADER_SIZE)
+}
+
+fn check(raw: &RawSystem) -> Vec<Diagnostic> {
+    let mut diagnostics = Vec::new();
+    let mut report = |severity, code, span: Range<usize>, message: String| {
+        diagnostics.push(Diagnostic {
+            severity,
+            code,
+            message,
+            span,
+        })
+    };
+
+    let mut partitions = BTreeSet::new();
+    let mut ports = BTreeMap::new();
+    for partition in &raw.partitions {
+        let name = partition.name.get_ref();
+        if name.is_empty() {
+            report(
+                Severity::Error,
+                "empty-name",
+                partition.name.span(),
+                "partition has an empty name".to_string(),
+            );
+        } else if !partitions.insert(name) {
+            report(
+                Severity::Error,
+                "duplicate-name",
+                partition.name.span(),
+                format!("partition {name:?} is declared more than once"),
+            );
+        }
+
+        for port in &partition.ports {
+            let name = port.name.get_ref();
+            let span = port.name.span();
+            if name.is_empty() {
+                report(
+                    Severity::Error,
+                    "empty-name",
+                    span.clone(),
+                    "port has an empty name".to_string(),
+                );
+            } else if ports.contains_key(name.as_str()) {
+                report(
+                    Severity::Error,
+                    "duplicate-name",
+                    span.clone(),
+                    format!("port {name:?} is declared more than once"),
+                );
+            } else {
+                ports.insert(name.as_str(), port);
+            }
+            if name.len() > MAX_NAME_LENGTH {
+                report(
+                    Severity::Error,
+                    "name-too-long",
+                    span.clone(),
+                    format!("port {name:?} is longer than {MAX_NAME_LENGTH} bytes"),
+                );
+            }
+
+            if port.max_message_size == 0 || port.max_nb_message == 0 {
+                report(
+                    Severity::Error,
+                    "zero-size",
+                    span,
+                    format!("port {name:?} has no room for a message"),
+                );
+                continue;
+            }
+            let size = segment_size(port.max_nb_message, port.max_message_size);
+            match size {
+                Some(size) if size <= MAX_SEGMENT_SIZE => {}
+                _ => report(
+                    Severity::Error,
+                    "segment-too-large",
+                    span,
+                    format!("port {name:?} needs a segment larger than {MAX_SEGMENT_SIZE} bytes"),
+                ),
+            }
+        }
+    }
+
+    let mut fed = BTreeSet::new();
+    let mut connected = BTreeSet::new();
+    for channel in &raw.channels {
+        let ends = [
+            (&channel.source, PortDirection::Source),
+            (&channel.destination, PortDirection::Destination),
+        ];
+        for (end, direction) in ends {
+            let name = end.get_ref();
+            match ports.get(name.as_str()) {
+                None => report(
+                    Severity::Error,
+                    "undeclared-port",
+                    end.span(),
+                    format!("channel names undeclared port {name:?}"),
+                ),
+                Some(port) if port.direction != direction => report(
+                    Severity::Error,
+                    "direction",
+                    end.span(),
+                    format!(
+                        "port {name:?} is declared as a {} port but used as a {}",
+                        direction_name(port.direction),
+                        direction_name(direction)
+                    ),
+                ),
+                Some(_) if direction == PortDirection::Destination && !connected.insert(name) => {
+                    report(
+                        Severity::Error,
+                        "reconnected-port",
+                        end.span(),
+                        format!("port {name:?} is already connected by another channel"),
+                    )
+                }
+                Some(_) => {}
+            }
+        }
+
+        let source = ports.get(channel.source.get_ref().as_str());
+        let destination = ports.get(channel.destination.get_ref().as_str());
+        if let (Some(source), Some(destination)) = (source, destination) {
+            if source.max_message_size != destination.max_message_size {
+                report(
+                    Severity::Error,
+                    "size-mismatch",
+                    channel.destination.span(),
+                    format!(
+                        "channel carries messages of up to {} bytes but {:?} accepts {}",
+                        source.max_message_size,
+                        destination.name.get_ref(),
+                        destination.max_message_size
+                    ),
+                );
+            }
+            if source.max_nb_message != destination.max_nb_message {
+                report(
+                    Severity::Error,
+                    "depth-mismatch",
+                    channel.destination.span(),
+                    format!(
+                        "channel ends hold {} and {} messages",
+                        source.max_nb_message, destination.max_nb_message
+                    ),
+                );
+            }
+            fed.insert(destination.name.get_ref());
+        }
+    }
+
+    for port in ports.values() {
+        if port.direction == PortDirection::Destination && !fed.contains(port.name.get_ref()) {
+            report(
+                Severity::Warning,
+                "no-source",
+                port.name.span(),
+                format!(
+                    "destination port {:?} is not fed by any channel",
+                    port.name.get_ref()
+                ),
+            );
+        }
+    }
+
+    diagnostics.sort_by_key(|diagnostic| diagnostic.span.start);
+    diagnostics
+}
+
+// === Tests ===
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::handle::PortHandle;
+    use crate::port::Message;
+    use crate::shared::unlink;
+
+    const SYSTEM: &str = r#"
+        [[partition]]
+        name = "navigation"
+
+        [[partition.port]]
+        name = "test_config_out"
+        direction = "source"
+        max_message_size = 16
+        max_nb_message = 4
+
+        [[partition]]
+        name = "display"
+
+        [[partition.port]]
+        name = "test_config_in"
+        direction = "destination"
+        max_message_size = 16
+        max_nb_message = 4
+        discipline = "priority"
+
+        [[channel]]
+        source = "test_config_out"
+        destination = "test_config_in"
+    "#;
+
+    #[test]
+    fn test_config_connects_channel_ends() {
+        let _ = unlink("test_config_out");
+        let config = SystemConfig::from_toml(SYSTEM).unwrap();
+        let destination = config.port("display", "test_config_in").unwrap();
+        assert_eq!(destination.discipline, QueuingDiscipline::Priority);
+        assert_eq!(config.recovery, RecoveryPolicy::Keep);
+        let reset = SystemConfig::from_toml(&format!("recovery = \"reset\"\n{SYSTEM}")).unwrap();
+        assert_eq!(reset.recovery, RecoveryPolicy::Reset);
+        assert_eq!(config.segment_name("test_config_in"), "test_config_out");
+
+        // Partitions only attach to the segments the port manager created.
+        assert_eq!(
+            config
+                .open_port("navigation", "test_config_out")
+                .map(|_| ()),
+            Err(PortError::ShmOpen)
+        );
+        let segments = config.create_ports().unwrap();
+        assert_eq!(segments.len(), 1);
+        assert_eq!(
+            segments["test_config_out"].discipline(PortDirection::Destination),
+            QueuingDiscipline::Priority
+        );
+
+        let source = config.open_port("navigation", "test_config_out").unwrap();
+        let destination = config.open_port("display", "test_config_in").unwrap();
+        assert_eq!(destination.capacity(), 4);
+
+        source.send(b"fix").unwrap();
+        let mut buf = [0u8; 16];
+        assert_eq!(destination.receive(&mut buf).map(|r| r.len), Ok(3));
+
+        // The segment is laid out as the typed port of the same geometry.
+        let typed = PortHandle::<Message<16>, 4>::open("test_config_out")
+            .unwrap()
+            .into_source();
+        typed.send(b"typed").unwrap();
+        assert_eq!(destination.receive(&mut buf).map(|r| r.len), Ok(5));
+        assert!(matches!(
+            PortHandle::<Message<16>, 8>::open("test_config_out"),
+            Err(PortError::LayoutMismatch)
+        ));
+
+        // A partition may only open the ports it declares.
+        assert_eq!(
+            config.open_port("navigation", "test_config_in").map(|_| ()),
+            Err(PortError::Undeclared)
+        );
+        assert_eq!(
+            config
+                .open_port("display", "test_config_missing")
+                .map(|_| ()),
+            Err(PortError::Undeclared)
+        );
+    }
+
+    #[test]
+    fn test_config_multicast_channel() {
+        let text = format!(
+            "{SYSTEM}{}",
+            r#"
+        [[partition]]
+        name = "recorder"
+
+        [[partition.port]]
+        name = "test_config_log"
+        direction = "destination"
+        max_message_size = 16
+        max_nb_message = 4
+
+        [[channel]]
+        source = "test_config_out"
+        destination = "test_config_log"
+        "#
+        );
+        for segment in ["test_config_in", "test_config_log"] {
+            let _ = unlink(segment);
+        }
+        let config = SystemConfig::from_toml(&text).unwrap();
+        assert!(config.is_multicast("test_config_out"));
+        assert_eq!(config.segment_name("test_config_log"), "test_config_log");
+        assert_eq!(
+            config
+                .open_port("navigation", "test_config_out")
+                .map(|_| ()),
+            Err(PortError::Multicast)
+        );
+
+        let segments = config.create_ports().unwrap();
+        let source = config
+            .open_multicast("navigation", "test_config_out")
+            .unwrap();
+        let display = config.open_port("display", "test_config_in").unwrap();
+        let recorder = config.open_port("recorder", "test_config_log").unwrap();
+        assert_eq!(source.send(b"fix").map(|d| d.delivered), Ok(2));
+
+        let mut buf = [0u8; 16];
+        assert_eq!(display.receive(&mut buf).map(|r| r.len), Ok(3));
+        assert_eq!(recorder.receive(&mut buf).map(|r| r.len), Ok(3));
+
+        // A destination still has a single source.
+        let refed = text.replace(
+            "source = \"test_config_out\"\n        destination = \"test_config_log\"",
+            "source = \"test_config_out\"\n        destination = \"test_config_in\"",
+        );
+        assert!(matches!(
+            SystemConfig::from_toml(&refed),
+            Err(ConfigError::Invalid(_))
+        ));
+
+        // A configuration built in code was never checked, and may name a
+        // destination no partition declares.
+        drop(segments);
+        let mut unchecked = config.clone();
+        unchecked
+            .partitions
+            .retain(|partition| partition.name != "recorder");
+        assert_eq!(
+            unchecked.create_ports().map(|_| ()),
+            Err(PortError::Undeclared)
+        );
+    }
+
+    #[test]
+    fn test_config_rejects_bad_systems() {
+        let mismatched = SYSTEM.replace(
+            "max_nb_message = 4\n        discipline",
+            "max_nb_message = 5\n        discipline",
+        );
+        assert!(matches!(
+            SystemConfig::from_toml(&mismatched),
+            Err(ConfigError::Invalid(_))
+        ));
+
+        let reversed = SYSTEM.replace(
+            "source = \"test_config_out\"\n        destination = \"test_config_in\"",
+            "source = \"test_config_in\"\n        destination = \"test_config_out\"",
+        );
+        assert!(matches!(
+            SystemConfig::from_toml(&reversed),
+            Err(ConfigError::Invalid(_))
+        ));
+
+        assert!(matches!(
+            SystemConfig::from_toml("[[partition]]\nname = \"p\"\nspeed = 3\n"),
+            Err(ConfigError::Parse(_))
+        ));
+    }
+
+    #[test]
+    fn test_diagnostics_point_at_lines() {
+        let text = r#"[[partition]]
+name = "p"
+
+[[partition.port]]
+name = "in"
+direction = "destination"
+max_message_size = 8
+max_nb_message = 2
+
+[[channel]]
+source = "in"
+destination = "out"
+"#;
+
+        let found: Vec<_> = diagnose(text)
+            .iter()
+            .map(|diagnostic| {
+                (
+                    diagnostic.line_column(text),
+                    diagnostic.severity,
+                    diagnostic.code,
+                )
+            })
+            .collect();
+        assert_eq!(
+            found,
+            [
+                ((5, 8), Severity::Warning, "no-source"),
+                ((11, 10), Severity::Error, "direction"),
+                ((12, 15), Severity::Error, "undeclared-port"),
+            ]
+        );
+
+        let parse = diagnose("[[partition]]\nname = \n");
+        assert_eq!(parse[0].code, "parse");
+        assert_eq!(parse[0].line_column("[[partition]]\nname = \n").0, 2);
+    }
+}
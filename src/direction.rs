@@ -0,0 +1,291 @@
+use core::marker::PhantomData;
+use core::time::Duration;
+
+use crate::error::PortError;
+use crate::handle::{DynamicPortHandle, PortHandle};
+use crate::port::{Message, Pod, Received, MSG_COUNT};
+use crate::wait::QueuingDiscipline;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(
+    feature = "std",
+    derive(serde::Deserialize),
+    serde(rename_all = "lowercase")
+)]
+#[repr(u32)]
+pub enum PortDirection {
+    Source = 0,
+    Destination = 1,
+}
+
+mod sealed {
+    pub trait Sealed {}
+}
+
+/// Type-level port direction, implemented only by [`Source`] and
+/// [`Destination`].
+pub trait Direction: sealed::Sealed {
+    const DIRECTION: PortDirection;
+}
+
+/// Marker for the sending end of a port.
+pub enum Source {}
+
+/// Marker for the receiving end of a port.
+pub enum Destination {}
+
+impl sealed::Sealed for Source {}
+impl sealed::Sealed for Destination {}
+
+impl Direction for Source {
+    const DIRECTION: PortDirection = PortDirection::Source;
+}
+
+impl Direction for Destination {
+    const DIRECTION: PortDirection = PortDirection::Destination;
+}
+
+/// A port handle restricted to one direction.
+///
+/// A source only offers the sending operations and a destination only the
+/// receiving ones, and neither hands out the handle underneath, so using the
+/// wrong end of a port does not compile:
+///
+/// ```compile_fail
+/// use master_project_queuing_port::PortHandle;
+///
+/// let source = PortHandle::<u32, 8>::create("doc_direction").unwrap().into_source();
+/// source.dequeue();
+/// ```
+pub struct DirectedPort<H, D: Direction> {
+    handle: H,
+    _direction: PhantomData<D>,
+}
+
+pub type SourcePort<T, const N: usize = MSG_COUNT> = DirectedPort<PortHandle<T, N>, Source>;
+pub type DestinationPort<T, const N: usize = MSG_COUNT> =
+    DirectedPort<PortHandle<T, N>, Destination>;
+pub type DynamicSourcePort = DirectedPort<DynamicPortHandle, Source>;
+pub type DynamicDestinationPort = DirectedPort<DynamicPortHandle, Destination>;
+
+impl<H, D: Direction> DirectedPort<H, D> {
+    pub fn new(handle: H) -> Self {
+        Self {
+            handle,
+            _direction: PhantomData,
+        }
+    }
+
+    pub fn direction(&self) -> PortDirection {
+        D::DIRECTION
+    }
+}
+
+impl<T: Pod, const N: usize> PortHandle<T, N> {
+    pub fn into_source(self) -> SourcePort<T, N> {
+        DirectedPort::new(self)
+    }
+
+    pub fn into_destination(self) -> DestinationPort<T, N> {
+        DirectedPort::new(self)
+    }
+}
+
+impl<T: Pod, const N: usize, D: Direction> DirectedPort<PortHandle<T, N>, D> {
+    /// Takes this end of the port, releasing callers blocked on it in
+    /// `discipline` order.
+    pub fn with_discipline(handle: PortHandle<T, N>, discipline: QueuingDiscipline) -> Self {
+        handle.set_discipline(D::DIRECTION, discipline);
+        Self::new(handle)
+    }
+}
+
+impl DynamicPortHandle {
+    pub fn into_source(self) -> DynamicSourcePort {
+        DirectedPort::new(self)
+    }
+
+    pub fn into_destination(self) -> DynamicDestinationPort {
+        DirectedPort::new(self)
+    }
+}
+
+impl<D: Direction> DirectedPort<DynamicPortHandle, D> {
+    /// Takes this end of the port, releasing callers blocked on it in
+    /// `discipline` order.
+    pub fn with_discipline(handle: DynamicPortHandle, discipline: QueuingDiscipline) -> Self {
+        handle.set_discipline(D::DIRECTION, discipline);
+        Self::new(handle)
+    }
+}
+
+// === Typed Ports ===
+
+impl<T: Pod, const N: usize, D: Direction> DirectedPort<PortHandle<T, N>, D> {
+    pub fn capacity(&self) -> usize {
+        self.handle.capacity()
+    }
+
+    pub fn len(&self) -> usize {
+        self.handle.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.handle.is_empty()
+    }
+}
+
+impl<T: Pod, const N: usize> SourcePort<T, N> {
+    pub fn enqueue(&self, item: T) -> Result<(), PortError> {
+        self.handle.enqueue(item)
+    }
+
+    pub fn enqueue_timeout(&self, item: T, timeout: Option<Duration>) -> Result<(), PortError> {
+        self.handle.enqueue_timeout(item, timeout)
+    }
+}
+
+impl<T: Pod, const N: usize> DestinationPort<T, N> {
+    pub fn dequeue(&self) -> Result<T, PortError> {
+        self.handle.dequeue()
+    }
+
+    pub fn dequeue_timeout(&self, timeout: Option<Duration>) -> Result<T, PortError> {
+        self.handle.dequeue_timeout(timeout)
+    }
+
+    pub fn clear(&self) {
+        self.handle.clear();
+    }
+
+    pub fn overflowed(&self) -> bool {
+        self.handle.overflowed()
+    }
+
+    pub fn take_overflow(&self) -> bool {
+        self.handle.take_overflow()
+    }
+}
+
+impl<const M: usize, const N: usize> SourcePort<Message<M>, N> {
+    pub fn send(&self, payload: &[u8]) -> Result<(), PortError> {
+        self.handle.send(payload)
+    }
+
+    pub fn send_timeout(&self, payload: &[u8], timeout: Option<Duration>) -> Result<(), PortError> {
+        self.handle.send_timeout(payload, timeout)
+    }
+}
+
+impl<const M: usize, const N: usize> DestinationPort<Message<M>, N> {
+    pub fn receive(&self, buf: &mut [u8]) -> Result<Received, PortError> {
+        self.handle.receive(buf)
+    }
+
+    pub fn receive_timeout(
+        &self,
+        buf: &mut [u8],
+        timeout: Option<Duration>,
+    ) -> Result<Received, PortError> {
+        self.handle.receive_timeout(buf, timeout)
+    }
+}
+
+// === Runtime-Sized Ports ===
+
+impl<D: Direction> DirectedPort<DynamicPortHandle, D> {
+    pub fn capacity(&self) -> usize {
+        self.handle.capacity()
+    }
+
+    pub fn max_message_size(&self) -> usize {
+        self.handle.max_message_size()
+    }
+
+    pub fn len(&self) -> usize {
+        self.handle.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.handle.is_empty()
+    }
+}
+
+impl DynamicSourcePort {
+    pub fn send(&self, payload: &[u8]) -> Result<(), PortError> {
+        self.handle.send(payload)
+    }
+
+    pub fn send_timeout(&self, payload: &[u8], timeout: Option<Duration>) -> Result<(), PortError> {
+        self.handle.send_timeout(payload, timeout)
+    }
+}
+
+impl DynamicDestinationPort {
+    pub fn receive(&self, buf: &mut [u8]) -> Result<Received, PortError> {
+        self.handle.receive(buf)
+    }
+
+    pub fn receive_timeout(
+        &self,
+        buf: &mut [u8],
+        timeout: Option<Duration>,
+    ) -> Result<Received, PortError> {
+        self.handle.receive_timeout(buf, timeout)
+    }
+
+    pub fn clear(&self) {
+        self.handle.clear();
+    }
+
+    pub fn overflowed(&self) -> bool {
+        self.handle.overflowed()
+    }
+}
+
+// === Tests ===
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::shared::unlink;
+
+    #[test]
+    fn test_typed_directions() {
+        let os_id = "test_typed_directions";
+        let _ = unlink(os_id);
+
+        let source = PortHandle::<u32, 4>::create(os_id).unwrap().into_source();
+        let destination = PortHandle::<u32, 4>::open(os_id)
+            .unwrap()
+            .into_destination();
+
+        assert_eq!(source.direction(), PortDirection::Source);
+        assert_eq!(destination.direction(), PortDirection::Destination);
+
+        source.enqueue(7).unwrap();
+        assert_eq!(destination.len(), 1);
+        assert_eq!(destination.dequeue(), Ok(7));
+    }
+
+    #[test]
+    fn test_dynamic_directions() {
+        let os_id = "test_dynamic_directions";
+        let _ = unlink(os_id);
+
+        let source = DynamicPortHandle::create(os_id, 4, 8)
+            .unwrap()
+            .into_source();
+        let destination = DynamicPortHandle::open(os_id).unwrap().into_destination();
+
+        source.send(b"ping").unwrap();
+        source.send(b"pong").unwrap();
+
+        let mut buf = [0u8; 8];
+        assert_eq!(destination.receive(&mut buf).map(|r| r.len), Ok(4));
+        assert_eq!(&buf[..4], b"ping");
+
+        destination.clear();
+        assert!(source.is_empty());
+    }
+}
@@ -0,0 +1,418 @@
+use core::mem::{align_of, size_of};
+use core::ptr::NonNull;
+use core::time::Duration;
+
+use crate::direction::PortDirection;
+use crate::error::PortError;
+use crate::futex::deadline_after;
+use crate::port::Received;
+use crate::ring::Ring;
+use crate::wait::QueuingDiscipline;
+
+// Geometry is written once by the creator and only read afterwards, so any
+// process mapping the segment can size the slot area from it.
+#[repr(C)]
+struct PortHeader {
+    slots: usize,
+    max_message_size: usize,
+    ring: Ring,
+}
+
+/// A byte-message queuing port whose slot count and maximum message size are
+/// chosen at creation time and stored in a header in front of the slots.
+///
+/// It can also view memory laid out as a [`MessagePort`](crate::MessagePort) whose geometry is
+/// only known at run time, since both keep a message in a slot the same way.
+///
+/// The port is a view onto memory it does not own; whoever created it must
+/// keep that memory mapped for as long as the port is used.
+pub struct DynamicQueuingPort {
+    ring: NonNull<Ring>,
+    slots: NonNull<u8>,
+    capacity: usize,
+    max_message_size: usize,
+    stride: usize,
+}
+
+unsafe impl Send for DynamicQueuingPort {}
+unsafe impl Sync for DynamicQueuingPort {}
+
+impl DynamicQueuingPort {
+    // Bytes per slot: the length and the payload, padded so the next slot
+    // stays aligned. `None` if that overflows.
+    pub(crate) const fn slot_stride(max_message_size: usize) -> Option<usize> {
+        match size_of::<usize>().checked_add(max_message_size) {
+            Some(unaligned) => unaligned
+                .div_ceil(align_of::<usize>())
+                .checked_mul(align_of::<usize>()),
+            None => None,
+        }
+    }
+
+    /// Bytes needed for a port of `slots` slots holding up to
+    /// `max_message_size` bytes each, or `None` if that overflows.
+    pub const fn required_size(slots: usize, max_message_size: usize) -> Option<usize> {
+        let Some(stride) = Self::slot_stride(max_message_size) else {
+            return None;
+        };
+        match slots.checked_mul(stride) {
+            Some(area) => area.checked_add(size_of::<PortHeader>()),
+            None => None,
+        }
+    }
+
+    /// Bytes taken by a [`MessagePort`](crate::MessagePort) of `slots` slots holding up to
+    /// `max_message_size` bytes each, or `None` if that overflows.
+    pub const fn message_port_size(slots: usize, max_message_size: usize) -> Option<usize> {
+        let Some(stride) = Self::slot_stride(max_message_size) else {
+            return None;
+        };
+        let Some(area) = slots.checked_mul(stride) else {
+            return None;
+        };
+        // The ring follows the slots, and the port is padded to its alignment.
+        let ring = align_of::<Ring>();
+        match area.div_ceil(ring).checked_mul(ring) {
+            Some(offset) => match offset.checked_add(size_of::<Ring>()) {
+                Some(end) => end.div_ceil(ring).checked_mul(ring),
+                None => None,
+            },
+            None => None,
+        }
+    }
+
+    // Checks a geometry fits in `len` bytes of memory, returning the stride.
+    fn check_geometry(
+        len: usize,
+        slots: usize,
+        max_message_size: usize,
+        required_size: Option<usize>,
+    ) -> Result<usize, PortError> {
+        if slots == 0 || max_message_size == 0 {
+            return Err(PortError::LayoutMismatch);
+        }
+        match (Self::slot_stride(max_message_size), required_size) {
+            (Some(stride), Some(size)) if size <= len => Ok(stride),
+            _ => Err(PortError::LayoutMismatch),
+        }
+    }
+
+    /// Writes a fresh header and empty ring into `memory`.
+    ///
+    /// # Safety
+    ///
+    /// `memory` must be valid for reads and writes of `len` bytes for as long
+    /// as the returned port (or any port attached to it) is used.
+    pub unsafe fn init(
+        memory: *mut u8,
+        len: usize,
+        slots: usize,
+        max_message_size: usize,
+    ) -> Result<Self, PortError> {
+        let stride = Self::check_geometry(
+            len,
+            slots,
+            max_message_size,
+            Self::required_size(slots, max_message_size),
+        )?;
+        let header = Self::header_ptr(memory)?;
+
+        header.as_ptr().write(PortHeader {
+            slots,
+            max_message_size,
+            ring: Ring::new(),
+        });
+
+        Ok(Self::from_header(header, stride))
+    }
+
+    /// Attaches to a port previously set up with [`DynamicQueuingPort::init`],
+    /// taking its geometry from the header.
+    ///
+    /// # Safety
+    ///
+    /// Same requirements as [`DynamicQueuingPort::init`]; the memory must
+    /// already hold an initialized port.
+    pub unsafe fn attach(memory: *mut u8, len: usize) -> Result<Self, PortError> {
+        if len < size_of::<PortHeader>() {
+            return Err(PortError::LayoutMismatch);
+        }
+        let header = Self::header_ptr(memory)?;
+
+        let (slots, max_message_size) = {
+            let header = header.as_ref();
+            (header.slots, header.max_message_size)
+        };
+        let stride = Self::check_geometry(
+            len,
+            slots,
+            max_message_size,
+            Self::required_size(slots, max_message_size),
+        )?;
+
+        Ok(Self::from_header(header, stride))
+    }
+
+    /// Views `memory` as a [`MessagePort`](crate::MessagePort) of `slots` slots holding up to
+    /// `max_message_size` bytes each. Zero-filled memory is an empty port.
+    ///
+    /// # Safety
+    ///
+    /// Same requirements as [`DynamicQueuingPort::init`]; the memory must
+    /// be zero-filled or already hold a port of this geometry.
+    pub unsafe fn attach_message_port(
+        memory: *mut u8,
+        len: usize,
+        slots: usize,
+        max_message_size: usize,
+    ) -> Result<Self, PortError> {
+        let required_size = Self::message_port_size(slots, max_message_size);
+        let stride = Self::check_geometry(len, slots, max_message_size, required_size)?;
+        let slots_ptr = NonNull::new(memory).ok_or(PortError::LayoutMismatch)?;
+        if memory.align_offset(align_of::<Ring>().max(align_of::<usize>())) != 0 {
+            return Err(PortError::LayoutMismatch);
+        }
+        let ring = (slots * stride).next_multiple_of(align_of::<Ring>());
+
+        Ok(Self {
+            ring: NonNull::new_unchecked(memory.add(ring) as *mut Ring),
+            slots: slots_ptr,
+            capacity: slots,
+            max_message_size,
+            stride,
+        })
+    }
+
+    fn header_ptr(memory: *mut u8) -> Result<NonNull<PortHeader>, PortError> {
+        let header = NonNull::new(memory as *mut PortHeader).ok_or(PortError::LayoutMismatch)?;
+        if header.as_ptr().align_offset(align_of::<PortHeader>()) != 0 {
+            return Err(PortError::LayoutMismatch);
+        }
+        Ok(header)
+    }
+
+    unsafe fn from_header(header: NonNull<PortHeader>, stride: usize) -> Self {
+        let slots =
+            NonNull::new_unchecked((header.as_ptr() as *mut u8).add(size_of::<PortHeader>()));
+        let header = header.as_ref();
+        Self {
+            ring: NonNull::from(&header.ring),
+            slots,
+            capacity: header.slots,
+            max_message_size: header.max_message_size,
+            stride,
+        }
+    }
+
+    fn ring(&self) -> &Ring {
+        unsafe { self.ring.as_ref() }
+    }
+
+    fn slot(&self, index: usize) -> *mut u8 {
+        unsafe { self.slots.as_ptr().add(index * self.stride) }
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    pub fn max_message_size(&self) -> usize {
+        self.max_message_size
+    }
+
+    pub fn len(&self) -> usize {
+        self.ring().len(self.capacity)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn clear(&self) {
+        self.ring().clear();
+    }
+
+    pub fn send(&self, payload: &[u8]) -> Result<(), PortError> {
+        if payload.len() > self.max_message_size() {
+            return Err(PortError::Oversize);
+        }
+
+        self.ring()
+            .push(self.capacity, |index| self.write_slot(index, payload))
+    }
+
+    pub fn receive(&self, buf: &mut [u8]) -> Result<Received, PortError> {
+        let len = self
+            .ring()
+            .pop(self.capacity, |index| self.read_slot(index, buf))?;
+        Ok(self.ring().received(len))
+    }
+
+    /// Like [`DynamicQueuingPort::send`], but waits for a free slot for up to
+    /// `timeout`, or indefinitely for `None`.
+    pub fn send_timeout(&self, payload: &[u8], timeout: Option<Duration>) -> Result<(), PortError> {
+        if payload.len() > self.max_message_size() {
+            return Err(PortError::Oversize);
+        }
+
+        let deadline = timeout.map(deadline_after);
+        self.ring()
+            .push_wait(self.capacity, deadline.as_ref(), |index| {
+                self.write_slot(index, payload)
+            })
+    }
+
+    /// Like [`DynamicQueuingPort::receive`], but waits for a message for up to
+    /// `timeout`, or indefinitely for `None`.
+    pub fn receive_timeout(
+        &self,
+        buf: &mut [u8],
+        timeout: Option<Duration>,
+    ) -> Result<Received, PortError> {
+        let deadline = timeout.map(deadline_after);
+        let len = self
+            .ring()
+            .pop_wait(self.capacity, deadline.as_ref(), |index| {
+                self.read_slot(index, buf)
+            })?;
+        Ok(self.ring().received(len))
+    }
+
+    /// Whether a message was turned away because the port was full since the
+    /// overflow was last reported.
+    pub fn overflowed(&self) -> bool {
+        self.ring().overflowed()
+    }
+
+    pub(crate) fn recover(&self, reset: bool) {
+        self.ring().recover(reset);
+    }
+
+    /// Blocked callers on the `direction` end of this port are released in
+    /// `discipline` order.
+    pub fn set_discipline(&self, direction: PortDirection, discipline: QueuingDiscipline) {
+        self.ring().waiters(direction).set_discipline(discipline);
+    }
+
+    pub fn discipline(&self, direction: PortDirection) -> QueuingDiscipline {
+        self.ring().waiters(direction).discipline()
+    }
+
+    /// Number of callers currently blocked on the `direction` end.
+    pub fn waiting(&self, direction: PortDirection) -> usize {
+        self.ring().waiters(direction).len()
+    }
+
+    fn write_slot(&self, index: usize, payload: &[u8]) {
+        unsafe {
+            let slot = self.slot(index);
+            (slot as *mut usize).write(payload.len());
+            core::ptr::copy_nonoverlapping(
+                payload.as_ptr(),
+                slot.add(size_of::<usize>()),
+                payload.len(),
+            );
+        }
+    }
+
+    fn read_slot(&self, index: usize, buf: &mut [u8]) -> Result<usize, PortError> {
+        unsafe {
+            let slot = self.slot(index);
+            let len = (slot as *const usize).read().min(self.max_message_size());
+            if len > buf.len() {
+                return Err(PortError::Oversize);
+            }
+
+            core::ptr::copy_nonoverlapping(slot.add(size_of::<usize>()), buf.as_mut_ptr(), len);
+            Ok(len)
+        }
+    }
+}
+
+// === Tests ===
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_geometry_from_header() {
+        let mut memory = vec![0u64; DynamicQueuingPort::required_size(4, 10).unwrap() / 8];
+        let ptr = memory.as_mut_ptr() as *mut u8;
+        let len = memory.len() * 8;
+
+        let creator = unsafe { DynamicQueuingPort::init(ptr, len, 4, 10) }.unwrap();
+        let attacher = unsafe { DynamicQueuingPort::attach(ptr, len) }.unwrap();
+
+        assert_eq!(attacher.capacity(), 4);
+        assert_eq!(attacher.max_message_size(), 10);
+
+        creator.send(b"hello").unwrap();
+        creator.send(b"0123456789").unwrap();
+        assert_eq!(creator.send(b"0123456789a"), Err(PortError::Oversize));
+
+        let mut buf = [0u8; 10];
+        assert_eq!(attacher.receive(&mut buf).map(|r| r.len), Ok(5));
+        assert_eq!(&buf[..5], b"hello");
+        assert_eq!(attacher.receive(&mut buf).map(|r| r.len), Ok(10));
+        assert_eq!(attacher.receive(&mut buf), Err(PortError::Empty));
+    }
+
+    #[test]
+    fn test_message_port_layout() {
+        use crate::port::MessagePort;
+
+        assert_eq!(
+            DynamicQueuingPort::message_port_size(4, 10),
+            Some(size_of::<MessagePort<10, 4>>())
+        );
+        assert_eq!(
+            DynamicQueuingPort::message_port_size(3, 16),
+            Some(size_of::<MessagePort<16, 3>>())
+        );
+        assert_eq!(
+            DynamicQueuingPort::message_port_size(8, usize::MAX / 4),
+            None
+        );
+
+        // Either view of the same memory sees what the other sent.
+        let typed = MessagePort::<10, 4>::new();
+        let port = unsafe {
+            DynamicQueuingPort::attach_message_port(
+                &typed as *const _ as *mut u8,
+                size_of::<MessagePort<10, 4>>(),
+                4,
+                10,
+            )
+        }
+        .unwrap();
+        let mut buf = [0u8; 10];
+        typed.send(b"typed").unwrap();
+        assert_eq!(port.receive(&mut buf).map(|r| r.len), Ok(5));
+        port.send(b"dynamic").unwrap();
+        assert_eq!(typed.receive(&mut buf).map(|r| r.len), Ok(7));
+        assert_eq!(&buf[..7], b"dynamic");
+        assert!(unsafe {
+            DynamicQueuingPort::attach_message_port(
+                &typed as *const _ as *mut u8,
+                size_of::<MessagePort<10, 4>>(),
+                8,
+                10,
+            )
+        }
+        .is_err());
+    }
+
+    #[test]
+    fn test_invalid_geometry() {
+        let mut memory = vec![0u64; 64];
+        let ptr = memory.as_mut_ptr() as *mut u8;
+
+        assert!(unsafe { DynamicQueuingPort::init(ptr, 512, 0, 8) }.is_err());
+        assert!(unsafe { DynamicQueuingPort::init(ptr, 512, 4, 0) }.is_err());
+        assert!(unsafe { DynamicQueuingPort::init(ptr, 512, 64, 8) }.is_err());
+        assert!(unsafe { DynamicQueuingPort::init(ptr, 512, 8, usize::MAX / 4) }.is_err());
+        assert_eq!(DynamicQueuingPort::required_size(8, usize::MAX / 4), None);
+        assert!(unsafe { DynamicQueuingPort::attach(ptr, 512) }.is_err());
+    }
+}
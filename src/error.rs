@@ -0,0 +1,61 @@
+use core::fmt;
+
+/// Why a port operation failed.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PortError {
+    /// The port has no free slot for another message.
+    Full,
+    /// The port holds no message.
+    Empty,
+    /// A message does not fit the port's slots or the caller's buffer.
+    Oversize,
+    /// A blocking call gave up before the port became ready.
+    Timeout,
+    /// The operation is not allowed on this end of the port.
+    WrongDirection,
+    /// The mapped memory does not hold a port of the expected type or
+    /// geometry.
+    LayoutMismatch,
+    /// The shared-memory segment could not be created.
+    ShmCreate,
+    /// The shared-memory segment could not be opened.
+    ShmOpen,
+    /// A segment with the requested name already exists.
+    AlreadyExists,
+    /// Every waiter entry on this end of the port is taken.
+    TooManyWaiters,
+    /// The system configuration does not declare the requested port.
+    Undeclared,
+    /// The port feeds several destinations and only opens as a multicast
+    /// source.
+    Multicast,
+    /// A process using the segment died and the recovery policy refuses to
+    /// reuse it.
+    OwnerDied,
+    /// Every attacher entry in the segment header is taken.
+    TooManyAttachers,
+}
+
+impl fmt::Display for PortError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            PortError::Full => "Queue full",
+            PortError::Empty => "Queue empty",
+            PortError::Oversize => "Message too large",
+            PortError::Timeout => "Timed out",
+            PortError::WrongDirection => "Wrong port direction",
+            PortError::LayoutMismatch => "Port layout mismatch",
+            PortError::ShmCreate => "Failed to create shared memory",
+            PortError::ShmOpen => "Failed to open shared memory",
+            PortError::AlreadyExists => "Port already exists",
+            PortError::TooManyWaiters => "Too many waiters",
+            PortError::Undeclared => "Port not declared in the configuration",
+            PortError::Multicast => "Port is a multicast source",
+            PortError::OwnerDied => "A process using the port died",
+            PortError::TooManyAttachers => "Too many processes attached",
+        })
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for PortError {}
@@ -0,0 +1,136 @@
+use core::sync::atomic::{AtomicU32, Ordering};
+use core::time::Duration;
+
+use libc::timespec;
+
+use crate::error::PortError;
+
+// Absolute CLOCK_MONOTONIC time after which a wait gives up.
+pub(crate) type Deadline = timespec;
+
+// Time on the CLOCK_MONOTONIC clock, which every process on the host shares.
+pub(crate) fn monotonic_now() -> Duration {
+    let now = deadline_after(Duration::ZERO);
+    Duration::new(now.tv_sec as u64, now.tv_nsec as u32)
+}
+
+pub(crate) fn deadline_after(timeout: Duration) -> Deadline {
+    let mut now = timespec {
+        tv_sec: 0,
+        tv_nsec: 0,
+    };
+    unsafe { libc::clock_gettime(libc::CLOCK_MONOTONIC, &mut now) };
+
+    let nanos = now.tv_nsec as u64 + u64::from(timeout.subsec_nanos());
+    timespec {
+        tv_sec: now
+            .tv_sec
+            .saturating_add(timeout.as_secs().min(i64::MAX as u64) as libc::time_t)
+            .saturating_add((nanos / 1_000_000_000) as libc::time_t),
+        tv_nsec: (nanos % 1_000_000_000) as _,
+    }
+}
+
+// === Processes ===
+
+pub(crate) fn current_pid() -> u32 {
+    unsafe { libc::getpid() as u32 }
+}
+
+// Whether process `pid` still exists. A process we may not signal exists too.
+pub(crate) fn process_alive(pid: u32) -> bool {
+    let result = unsafe { libc::kill(pid as libc::pid_t, 0) };
+    result == 0 || unsafe { *libc::__errno_location() } != libc::ESRCH
+}
+
+// === Futexes ===
+
+// Sleeps while `word` still holds `expected`. Returns false once `deadline`
+// has passed; wakeups, spurious or not, return true. The futex is not
+// process-private, so waiters in other processes mapping the same segment are
+// woken too.
+#[cfg(target_os = "linux")]
+fn wait(word: &AtomicU32, expected: u32, deadline: Option<&Deadline>) -> bool {
+    let timeout = deadline.map_or(core::ptr::null(), |deadline| deadline as *const timespec);
+    let result = unsafe {
+        libc::syscall(
+            libc::SYS_futex,
+            word.as_ptr(),
+            libc::FUTEX_WAIT_BITSET,
+            expected,
+            timeout,
+            core::ptr::null::<u32>(),
+            libc::FUTEX_BITSET_MATCH_ANY,
+        )
+    };
+
+    result == 0 || unsafe { *libc::__errno_location() } != libc::ETIMEDOUT
+}
+
+#[cfg(target_os = "linux")]
+fn wake_all(word: &AtomicU32) {
+    unsafe {
+        libc::syscall(libc::SYS_futex, word.as_ptr(), libc::FUTEX_WAKE, i32::MAX);
+    }
+}
+
+// Without futexes the waiter yields its time slice and polls instead.
+#[cfg(not(target_os = "linux"))]
+fn wait(word: &AtomicU32, expected: u32, deadline: Option<&Deadline>) -> bool {
+    unsafe { libc::sched_yield() };
+    let passed = deadline.is_some_and(|deadline| {
+        let now = deadline_after(Duration::ZERO);
+        (now.tv_sec, now.tv_nsec) >= (deadline.tv_sec, deadline.tv_nsec)
+    });
+    word.load(Ordering::Acquire) != expected || !passed
+}
+
+#[cfg(not(target_os = "linux"))]
+fn wake_all(_word: &AtomicU32) {}
+
+/// A wakeup channel that lives in shared memory. All-zero is a valid initial
+/// state.
+#[repr(C)]
+pub(crate) struct Event {
+    seq: AtomicU32,
+    waiters: AtomicU32,
+}
+
+impl Event {
+    pub(crate) const fn new() -> Self {
+        Self {
+            seq: AtomicU32::new(0),
+            waiters: AtomicU32::new(0),
+        }
+    }
+
+    // Wakes every waiter. Call after the state the waiters check has changed.
+    pub(crate) fn notify(&self) {
+        self.seq.fetch_add(1, Ordering::SeqCst);
+        if self.waiters.load(Ordering::SeqCst) > 0 {
+            wake_all(&self.seq);
+        }
+    }
+
+    // Blocks while `blocked` holds, until `deadline` if there is one.
+    pub(crate) fn wait_while(
+        &self,
+        mut blocked: impl FnMut() -> bool,
+        deadline: Option<&Deadline>,
+    ) -> Result<(), PortError> {
+        loop {
+            self.waiters.fetch_add(1, Ordering::SeqCst);
+            let seen = self.seq.load(Ordering::SeqCst);
+            let still_blocked = blocked();
+            let timed_out = still_blocked && !wait(&self.seq, seen, deadline);
+            self.waiters.fetch_sub(1, Ordering::SeqCst);
+
+            if !still_blocked {
+                return Ok(());
+            }
+            if timed_out {
+                return Err(PortError::Timeout);
+            }
+        }
+    }
+}
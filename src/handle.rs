@@ -0,0 +1,959 @@
+use core::marker::PhantomData;
+use core::mem::align_of;
+use core::ops::Deref;
+use core::ptr::NonNull;
+use core::time::Duration;
+
+use crate::dynamic::DynamicQueuingPort;
+use crate::error::PortError;
+use crate::header::{tagged_hash, type_hash, RecoveryPolicy, SegmentLayout};
+use crate::mpmc::MpmcQueuingPort;
+use crate::port::{message_tag, Pod, QueuingPort, MSG_COUNT};
+use crate::sampling::SamplingPort;
+use crate::shared::{map_port, map_port_segment, OpenMode, OpenOptions, Segment};
+
+const QUEUING_KIND: &str = "QueuingPort";
+
+/// An owned mapping of a [`QueuingPort`] living in its own shared-memory
+/// segment.
+///
+/// `create` fails if the segment already exists, `open` fails if it does not,
+/// and `open_or_create` attaches to an existing segment or creates a new one.
+/// Opening never touches the indices or the buffer. Every segment starts with
+/// a header recording the port type, message type and geometry, and opening
+/// a segment whose header does not match fails with
+/// [`PortError::LayoutMismatch`]. If a process using the segment died,
+/// opening it applies a [`RecoveryPolicy`]: [`RecoveryPolicy::Keep`] unless
+/// opened with `create_with_policy` or `open_with_policy`, so each open
+/// chooses its own. Dropping the handle unmaps the segment, and
+/// the creating handle also removes it unless it was made persistent with
+/// [`Segment::set_lifetime`].
+///
+/// The geometry is checked at compile time, as for [`QueuingPort::new`]:
+///
+/// ```compile_fail
+/// use master_project_queuing_port::PortHandle;
+///
+/// let port = PortHandle::<u32, 0>::create("doc_no_slots");
+/// ```
+pub struct PortHandle<T: Pod, const N: usize = MSG_COUNT> {
+    port: NonNull<QueuingPort<T, N>>,
+    segment: Segment,
+    _marker: PhantomData<QueuingPort<T, N>>,
+}
+
+// The handle only hands out `&QueuingPort`, which is `Sync` because callers on
+// the same end of its ring take turns, and the mapping itself may be unmapped
+// from any thread.
+unsafe impl<T: Pod, const N: usize> Send for PortHandle<T, N> {}
+unsafe impl<T: Pod, const N: usize> Sync for PortHandle<T, N> {}
+
+impl<T: Pod, const N: usize> PortHandle<T, N> {
+    pub fn create(os_id: &str) -> Result<Self, PortError> {
+        Self::create_with_policy(os_id, RecoveryPolicy::Keep)
+    }
+
+    pub fn create_with_policy(os_id: &str, policy: RecoveryPolicy) -> Result<Self, PortError> {
+        Self::map(os_id, OpenMode::Create, policy)
+    }
+
+    pub fn open(os_id: &str) -> Result<Self, PortError> {
+        Self::open_with_policy(os_id, RecoveryPolicy::Keep)
+    }
+
+    pub fn open_with_policy(os_id: &str, policy: RecoveryPolicy) -> Result<Self, PortError> {
+        Self::map(os_id, OpenMode::Open, policy)
+    }
+
+    pub fn open_or_create(os_id: &str) -> Result<Self, PortError> {
+        Self::map(os_id, OpenMode::OpenOrCreate, RecoveryPolicy::Keep)
+    }
+
+    fn map(os_id: &str, mode: OpenMode, policy: RecoveryPolicy) -> Result<Self, PortError> {
+        let () = QueuingPort::<T, N>::GEOMETRY_OK;
+
+        let layout = SegmentLayout::new::<T>(
+            QUEUING_KIND,
+            QueuingPort::<T, N>::CAPACITY,
+            QueuingPort::<T, N>::SLOT_SIZE,
+        );
+        let (segment, port) = map_port::<QueuingPort<T, N>>(
+            os_id,
+            OpenOptions { mode, policy },
+            &layout,
+            |_| {},
+            QueuingPort::recover,
+        )?;
+        Ok(Self {
+            port,
+            segment,
+            _marker: PhantomData,
+        })
+    }
+
+    /// The shared-memory segment the port lives in.
+    pub fn segment(&self) -> &Segment {
+        &self.segment
+    }
+
+    pub fn segment_mut(&mut self) -> &mut Segment {
+        &mut self.segment
+    }
+}
+
+impl<T: Pod, const N: usize> Deref for PortHandle<T, N> {
+    type Target = QueuingPort<T, N>;
+
+    fn deref(&self) -> &QueuingPort<T, N> {
+        unsafe { self.port.as_ref() }
+    }
+}
+
+// === Runtime-Sized Shared Ports ===
+
+const DYNAMIC_KIND: &str = "DynamicQueuingPort";
+
+/// An owned mapping of a [`DynamicQueuingPort`] living in its own
+/// shared-memory segment.
+///
+/// The creator decides the geometry; processes that open the segment later
+/// read it back from the header. A message port segment is laid out as the
+/// typed `PortHandle<Message<max_message_size>, slots>` instead, with no
+/// geometry stored, so programs holding either handle share it. Dropping
+/// behaves as for [`PortHandle`].
+pub struct DynamicPortHandle {
+    port: DynamicQueuingPort,
+    segment: Segment,
+}
+
+unsafe impl Send for DynamicPortHandle {}
+unsafe impl Sync for DynamicPortHandle {}
+
+impl DynamicPortHandle {
+    pub fn create(os_id: &str, slots: usize, max_message_size: usize) -> Result<Self, PortError> {
+        Self::create_with_policy(os_id, slots, max_message_size, RecoveryPolicy::Keep)
+    }
+
+    pub fn create_with_policy(
+        os_id: &str,
+        slots: usize,
+        max_message_size: usize,
+        policy: RecoveryPolicy,
+    ) -> Result<Self, PortError> {
+        Self::map(os_id, slots, max_message_size, OpenMode::Create, policy)
+    }
+
+    pub fn open(os_id: &str) -> Result<Self, PortError> {
+        Self::open_with_policy(os_id, RecoveryPolicy::Keep)
+    }
+
+    pub fn open_with_policy(os_id: &str, policy: RecoveryPolicy) -> Result<Self, PortError> {
+        Self::map(os_id, 0, 0, OpenMode::Open, policy)
+    }
+
+    /// Creates `os_id` as a message port segment.
+    pub fn create_message_port(
+        os_id: &str,
+        slots: usize,
+        max_message_size: usize,
+        policy: RecoveryPolicy,
+    ) -> Result<Self, PortError> {
+        Self::map_message_port(os_id, slots, max_message_size, OpenMode::Create, policy)
+    }
+
+    /// Attaches to the message port segment `os_id`, which must have the
+    /// geometry expected.
+    pub fn open_message_port(
+        os_id: &str,
+        slots: usize,
+        max_message_size: usize,
+        policy: RecoveryPolicy,
+    ) -> Result<Self, PortError> {
+        Self::map_message_port(os_id, slots, max_message_size, OpenMode::Open, policy)
+    }
+
+    /// Attaches to `os_id` if it exists, in which case its geometry must match
+    /// the one requested, or creates it otherwise.
+    pub fn open_or_create(
+        os_id: &str,
+        slots: usize,
+        max_message_size: usize,
+    ) -> Result<Self, PortError> {
+        Self::map(
+            os_id,
+            slots,
+            max_message_size,
+            OpenMode::OpenOrCreate,
+            RecoveryPolicy::Keep,
+        )?
+        .check_geometry(slots, max_message_size)
+    }
+
+    fn check_geometry(self, slots: usize, max_message_size: usize) -> Result<Self, PortError> {
+        if self.capacity() != slots || self.max_message_size() != max_message_size {
+            return Err(PortError::LayoutMismatch);
+        }
+        Ok(self)
+    }
+
+    fn map(
+        os_id: &str,
+        slots: usize,
+        max_message_size: usize,
+        mode: OpenMode,
+        policy: RecoveryPolicy,
+    ) -> Result<Self, PortError> {
+        let (segment, port) = map_port_segment(
+            os_id,
+            DynamicQueuingPort::required_size(slots, max_message_size)
+                .ok_or(PortError::LayoutMismatch)?,
+            OpenOptions { mode, policy },
+            type_hash::<u8>(DYNAMIC_KIND),
+            |memory, len| {
+                let port =
+                    unsafe { DynamicQueuingPort::init(memory, len, slots, max_message_size)? };
+                let layout = Self::layout(&port);
+                Ok((port, layout))
+            },
+            |memory, len, stamped| {
+                let port = unsafe { DynamicQueuingPort::attach(memory, len)? };
+                if *stamped != Self::layout(&port) {
+                    return Err(PortError::LayoutMismatch);
+                }
+                Ok(port)
+            },
+            DynamicQueuingPort::recover,
+        )?;
+        Ok(Self { port, segment })
+    }
+
+    /// The shared-memory segment the port lives in.
+    pub fn segment(&self) -> &Segment {
+        &self.segment
+    }
+
+    pub fn segment_mut(&mut self) -> &mut Segment {
+        &mut self.segment
+    }
+
+    fn layout(port: &DynamicQueuingPort) -> SegmentLayout {
+        SegmentLayout::new::<u8>(DYNAMIC_KIND, port.capacity(), port.max_message_size())
+    }
+
+    fn map_message_port(
+        os_id: &str,
+        slots: usize,
+        max_message_size: usize,
+        mode: OpenMode,
+        policy: RecoveryPolicy,
+    ) -> Result<Self, PortError> {
+        let stride =
+            DynamicQueuingPort::slot_stride(max_message_size).ok_or(PortError::LayoutMismatch)?;
+        // What `PortHandle::<Message<max_message_size>, slots>` stamps.
+        let layout = SegmentLayout {
+            type_hash: tagged_hash(
+                QUEUING_KIND,
+                message_tag(max_message_size),
+                stride,
+                align_of::<usize>(),
+            ),
+            capacity: slots as u64,
+            slot_size: stride as u64,
+        };
+        let port = |memory, len| unsafe {
+            DynamicQueuingPort::attach_message_port(memory, len, slots, max_message_size)
+        };
+
+        let (segment, port) = map_port_segment(
+            os_id,
+            DynamicQueuingPort::message_port_size(slots, max_message_size)
+                .ok_or(PortError::LayoutMismatch)?,
+            OpenOptions { mode, policy },
+            layout.type_hash,
+            |memory, len| Ok((port(memory, len)?, layout)),
+            |memory, len, stamped| {
+                if *stamped != layout {
+                    return Err(PortError::LayoutMismatch);
+                }
+                port(memory, len)
+            },
+            DynamicQueuingPort::recover,
+        )?;
+        Ok(Self { port, segment })
+    }
+}
+
+impl Deref for DynamicPortHandle {
+    type Target = DynamicQueuingPort;
+
+    fn deref(&self) -> &DynamicQueuingPort {
+        &self.port
+    }
+}
+
+// === Sampling Ports ===
+
+/// An owned mapping of a [`SamplingPort`] living in its own shared-memory
+/// segment.
+///
+/// The creator sets the refresh period; processes that open the segment later
+/// use the one already stored there. Dropping behaves as for [`PortHandle`].
+pub struct SamplingPortHandle<const M: usize> {
+    port: NonNull<SamplingPort<M>>,
+    segment: Segment,
+}
+
+unsafe impl<const M: usize> Send for SamplingPortHandle<M> {}
+unsafe impl<const M: usize> Sync for SamplingPortHandle<M> {}
+
+impl<const M: usize> SamplingPortHandle<M> {
+    pub fn create(os_id: &str, refresh_period: Duration) -> Result<Self, PortError> {
+        Self::create_with_policy(os_id, refresh_period, RecoveryPolicy::Keep)
+    }
+
+    pub fn create_with_policy(
+        os_id: &str,
+        refresh_period: Duration,
+        policy: RecoveryPolicy,
+    ) -> Result<Self, PortError> {
+        Self::map(os_id, refresh_period, OpenMode::Create, policy)
+    }
+
+    pub fn open(os_id: &str) -> Result<Self, PortError> {
+        Self::open_with_policy(os_id, RecoveryPolicy::Keep)
+    }
+
+    pub fn open_with_policy(os_id: &str, policy: RecoveryPolicy) -> Result<Self, PortError> {
+        Self::map(os_id, Duration::ZERO, OpenMode::Open, policy)
+    }
+
+    pub fn open_or_create(os_id: &str, refresh_period: Duration) -> Result<Self, PortError> {
+        Self::map(
+            os_id,
+            refresh_period,
+            OpenMode::OpenOrCreate,
+            RecoveryPolicy::Keep,
+        )
+    }
+
+    fn map(
+        os_id: &str,
+        refresh_period: Duration,
+        mode: OpenMode,
+        policy: RecoveryPolicy,
+    ) -> Result<Self, PortError> {
+        let layout = SegmentLayout::new::<u8>("SamplingPort", 1, M);
+        let (segment, port) = map_port::<SamplingPort<M>>(
+            os_id,
+            OpenOptions { mode, policy },
+            &layout,
+            |port| port.set_refresh_period(refresh_period),
+            SamplingPort::recover,
+        )?;
+        Ok(Self { port, segment })
+    }
+
+    /// The shared-memory segment the port lives in.
+    pub fn segment(&self) -> &Segment {
+        &self.segment
+    }
+
+    pub fn segment_mut(&mut self) -> &mut Segment {
+        &mut self.segment
+    }
+}
+
+impl<const M: usize> Deref for SamplingPortHandle<M> {
+    type Target = SamplingPort<M>;
+
+    fn deref(&self) -> &SamplingPort<M> {
+        unsafe { self.port.as_ref() }
+    }
+}
+
+// === Multi-Producer Ports ===
+
+/// An owned mapping of an [`MpmcQueuingPort`] living in its own
+/// shared-memory segment. Opening and dropping behave as for [`PortHandle`].
+pub struct MpmcPortHandle<T: Pod, const N: usize = MSG_COUNT> {
+    port: NonNull<MpmcQueuingPort<T, N>>,
+    segment: Segment,
+}
+
+unsafe impl<T: Pod, const N: usize> Send for MpmcPortHandle<T, N> {}
+unsafe impl<T: Pod, const N: usize> Sync for MpmcPortHandle<T, N> {}
+
+impl<T: Pod, const N: usize> MpmcPortHandle<T, N> {
+    pub fn create(os_id: &str) -> Result<Self, PortError> {
+        Self::create_with_policy(os_id, RecoveryPolicy::Keep)
+    }
+
+    pub fn create_with_policy(os_id: &str, policy: RecoveryPolicy) -> Result<Self, PortError> {
+        Self::map(os_id, OpenMode::Create, policy)
+    }
+
+    pub fn open(os_id: &str) -> Result<Self, PortError> {
+        Self::open_with_policy(os_id, RecoveryPolicy::Keep)
+    }
+
+    pub fn open_with_policy(os_id: &str, policy: RecoveryPolicy) -> Result<Self, PortError> {
+        Self::map(os_id, OpenMode::Open, policy)
+    }
+
+    pub fn open_or_create(os_id: &str) -> Result<Self, PortError> {
+        Self::map(os_id, OpenMode::OpenOrCreate, RecoveryPolicy::Keep)
+    }
+
+    fn map(os_id: &str, mode: OpenMode, policy: RecoveryPolicy) -> Result<Self, PortError> {
+        let () = MpmcQueuingPort::<T, N>::GEOMETRY_OK;
+
+        let layout = SegmentLayout::new::<T>(
+            "MpmcQueuingPort",
+            MpmcQueuingPort::<T, N>::CAPACITY,
+            MpmcQueuingPort::<T, N>::SLOT_SIZE,
+        );
+        let (segment, port) = map_port::<MpmcQueuingPort<T, N>>(
+            os_id,
+            OpenOptions { mode, policy },
+            &layout,
+            |_| {},
+            MpmcQueuingPort::recover,
+        )?;
+        Ok(Self { port, segment })
+    }
+
+    /// The shared-memory segment the port lives in.
+    pub fn segment(&self) -> &Segment {
+        &self.segment
+    }
+
+    pub fn segment_mut(&mut self) -> &mut Segment {
+        &mut self.segment
+    }
+}
+
+impl<T: Pod, const N: usize> Deref for MpmcPortHandle<T, N> {
+    type Target = MpmcQueuingPort<T, N>;
+
+    fn deref(&self) -> &MpmcQueuingPort<T, N> {
+        unsafe { self.port.as_ref() }
+    }
+}
+
+// === Tests ===
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::direction::PortDirection;
+    use crate::header::MAX_ATTACHERS;
+    use crate::port::Message;
+    use crate::shared::{map_segment, unlink};
+    use std::sync::Arc;
+    use std::thread;
+    use std::time::Duration;
+
+    #[test]
+    fn test_dynamic_port_shared_geometry() {
+        let os_id = "test_dynamic_port";
+        let _ = unlink(os_id);
+
+        let creator = DynamicPortHandle::create(os_id, 8, 64).unwrap();
+        let attacher = DynamicPortHandle::open(os_id).unwrap();
+
+        assert_eq!(attacher.capacity(), 8);
+        assert_eq!(attacher.max_message_size(), 64);
+        assert!(matches!(
+            DynamicPortHandle::create("test_dynamic_huge", 8, usize::MAX / 4),
+            Err(PortError::LayoutMismatch)
+        ));
+
+        creator.send(&[7u8; 64]).unwrap();
+
+        let mut buf = [0u8; 64];
+        assert_eq!(attacher.receive(&mut buf).map(|r| r.len), Ok(64));
+        assert_eq!(buf, [7u8; 64]);
+    }
+
+    #[test]
+    fn test_open_does_not_reset_port() {
+        let os_id = "test_open_existing";
+        let _ = unlink(os_id);
+
+        let creator = PortHandle::<u32, 8>::create(os_id).unwrap();
+        creator.enqueue(1).unwrap();
+        creator.enqueue(2).unwrap();
+
+        let attacher = PortHandle::<u32, 8>::open(os_id).unwrap();
+        let other = PortHandle::<u32, 8>::open_or_create(os_id).unwrap();
+
+        assert_eq!(attacher.dequeue(), Ok(1));
+        assert_eq!(other.dequeue(), Ok(2));
+        assert_eq!(creator.dequeue(), Err(PortError::Empty));
+    }
+
+    #[test]
+    fn test_attachers_counted_per_process() {
+        let os_id = "test_attachers";
+        let _ = unlink(os_id);
+        let _creator = PortHandle::<u32, 8>::create(os_id).unwrap();
+
+        // Handles of one process share its entry, however many it opens.
+        let handles: Vec<_> = (0..2 * MAX_ATTACHERS)
+            .map(|_| PortHandle::<u32, 8>::open(os_id).unwrap())
+            .collect();
+        drop(handles);
+        let kept = PortHandle::<u32, 8>::open(os_id).unwrap();
+
+        // Every other entry is still free for other processes, which report
+        // back once attached and stay until the last open is done.
+        let mut pipe = [0; 2];
+        assert_eq!(unsafe { libc::pipe(pipe.as_mut_ptr()) }, 0);
+        let children: Vec<_> = (0..MAX_ATTACHERS - 1)
+            .map(|_| {
+                crash_after(|| {
+                    let attached = PortHandle::<u32, 8>::open(os_id).is_ok_and(|handle| {
+                        core::mem::forget(handle);
+                        true
+                    });
+                    unsafe { libc::write(pipe[1], [attached as u8].as_ptr().cast(), 1) };
+                    thread::sleep(Duration::from_millis(500));
+                })
+            })
+            .collect();
+        for _ in &children {
+            let mut attached = 0u8;
+            assert_eq!(
+                unsafe { libc::read(pipe[0], (&mut attached as *mut u8).cast(), 1) },
+                1
+            );
+            assert_eq!(attached, 1);
+        }
+        assert!(PortHandle::<u32, 8>::open(os_id).is_ok());
+
+        for child in children {
+            unsafe {
+                libc::kill(child, libc::SIGKILL);
+                libc::waitpid(child, core::ptr::null_mut(), 0);
+            }
+        }
+        unsafe {
+            libc::close(pipe[0]);
+            libc::close(pipe[1]);
+        }
+        drop(kept);
+    }
+
+    #[test]
+    fn test_create_and_open_modes() {
+        let os_id = "test_open_modes";
+        let _ = unlink(os_id);
+
+        assert!(PortHandle::<u32, 8>::open(os_id).is_err());
+
+        let _creator = PortHandle::<u32, 8>::open_or_create(os_id).unwrap();
+        assert!(matches!(
+            PortHandle::<u32, 8>::create(os_id),
+            Err(PortError::AlreadyExists)
+        ));
+        assert!(matches!(
+            PortHandle::<u32, 64>::open(os_id),
+            Err(PortError::LayoutMismatch)
+        ));
+    }
+
+    #[test]
+    fn test_segment_header_rejects_other_layouts() {
+        let os_id = "test_segment_header";
+        let _ = unlink(os_id);
+
+        let _creator = PortHandle::<u32, 8>::create(os_id).unwrap();
+        assert!(PortHandle::<u32, 8>::open(os_id).is_ok());
+        assert!(matches!(
+            PortHandle::<i32, 8>::open(os_id),
+            Err(PortError::LayoutMismatch)
+        ));
+        assert!(matches!(
+            PortHandle::<u32, 4>::open(os_id),
+            Err(PortError::LayoutMismatch)
+        ));
+        assert!(matches!(
+            DynamicPortHandle::open(os_id),
+            Err(PortError::LayoutMismatch)
+        ));
+        assert!(matches!(
+            SamplingPortHandle::<4>::open(os_id),
+            Err(PortError::LayoutMismatch)
+        ));
+
+        // Messages whose capacities pad to the same slot size.
+        let messages = "test_segment_header_messages";
+        let _ = unlink(messages);
+        let _creator = PortHandle::<Message<8>, 4>::create(messages).unwrap();
+        assert!(matches!(
+            PortHandle::<Message<5>, 4>::open(messages),
+            Err(PortError::LayoutMismatch)
+        ));
+
+        // A segment that was never stamped, or holds something else entirely.
+        let garbage = "test_segment_garbage";
+        let _ = unlink(garbage);
+        let (shmem, _) = map_segment(garbage, 4096, OpenMode::Create).unwrap();
+        assert!(matches!(
+            PortHandle::<u32, 8>::open(garbage),
+            Err(PortError::LayoutMismatch)
+        ));
+        unsafe { shmem.as_ptr().write_bytes(0xa5, shmem.len()) };
+        assert!(matches!(
+            DynamicPortHandle::open(garbage),
+            Err(PortError::LayoutMismatch)
+        ));
+    }
+
+    #[derive(Clone, Copy)]
+    #[repr(C)]
+    struct Reading {
+        value: u32,
+    }
+
+    unsafe impl Pod for Reading {
+        const TYPE_TAG: u64 = crate::type_tag("Reading");
+    }
+
+    // The same message type as declared by a separately built program.
+    mod other {
+        #[derive(Clone, Copy)]
+        #[repr(C)]
+        pub struct Reading {
+            pub value: u32,
+        }
+
+        unsafe impl crate::Pod for Reading {
+            const TYPE_TAG: u64 = crate::type_tag("Reading");
+        }
+    }
+
+    #[test]
+    fn test_segment_header_matches_type_tags() {
+        let os_id = "test_segment_tags";
+        let _ = unlink(os_id);
+
+        // The header records the tag, not the path the compiler names.
+        let creator = PortHandle::<Reading, 8>::create(os_id).unwrap();
+        creator.enqueue(Reading { value: 7 }).unwrap();
+        let attacher = PortHandle::<other::Reading, 8>::open(os_id).unwrap();
+        assert_eq!(attacher.dequeue().map(|reading| reading.value), Ok(7));
+        assert!(matches!(
+            PortHandle::<u32, 8>::open(os_id),
+            Err(PortError::LayoutMismatch)
+        ));
+    }
+
+    // Forks a child that runs `f` and exits without dropping anything, as a
+    // crashed process would.
+    fn crash_after(f: impl FnOnce()) -> libc::pid_t {
+        match unsafe { libc::fork() } {
+            0 => {
+                f();
+                unsafe { libc::_exit(0) };
+            }
+            child => child,
+        }
+    }
+
+    #[test]
+    fn test_recovery_from_dead_processes() {
+        let os_id = "test_recovery";
+        let _ = unlink(os_id);
+        let port = DynamicPortHandle::create(os_id, 3, 8).unwrap();
+        let mut buf = [0u8; 8];
+        let mut status = 0;
+
+        // A producer fills the queue and is killed while blocked sending, so
+        // it still holds its place in line.
+        let child = crash_after(|| {
+            let producer = DynamicPortHandle::open(os_id).unwrap();
+            for _ in 0..3 {
+                producer.send(b"kept").unwrap();
+            }
+            let _ = producer.send_timeout(b"lost", None);
+        });
+        while port.waiting(PortDirection::Source) == 0 {
+            thread::sleep(Duration::from_millis(1));
+        }
+        unsafe {
+            libc::kill(child, libc::SIGKILL);
+            libc::waitpid(child, &mut status, 0);
+        }
+        assert_eq!(port.waiting(PortDirection::Source), 1);
+
+        assert!(matches!(
+            DynamicPortHandle::open_with_policy(os_id, RecoveryPolicy::Refuse),
+            Err(PortError::OwnerDied)
+        ));
+
+        // The restarted producer rejoins behind the messages already queued.
+        let producer = DynamicPortHandle::open(os_id).unwrap();
+        assert_eq!(producer.segment().generation(), 1);
+        assert_eq!(port.waiting(PortDirection::Source), 0);
+        assert_eq!(port.len(), 3);
+        port.receive(&mut buf).unwrap();
+        producer
+            .send_timeout(b"new", Some(Duration::from_secs(5)))
+            .unwrap();
+        drop(producer);
+
+        // A consumer that crashed without detaching; resetting drops the queue.
+        let child = crash_after(|| core::mem::forget(DynamicPortHandle::open(os_id).unwrap()));
+        unsafe { libc::waitpid(child, &mut status, 0) };
+        let consumer = DynamicPortHandle::open_with_policy(os_id, RecoveryPolicy::Reset).unwrap();
+        assert_eq!(consumer.segment().generation(), 2);
+        assert_eq!(consumer.len(), 0);
+        assert_eq!(consumer.receive(&mut buf), Err(PortError::Empty));
+
+        // A segment left behind by a crashed creator is taken over by the
+        // next process creating it.
+        let stale = "test_recovery_stale";
+        let _ = unlink(stale);
+        let child = crash_after(|| core::mem::forget(PortHandle::<u32, 4>::create(stale).unwrap()));
+        unsafe { libc::waitpid(child, &mut status, 0) };
+        assert!(matches!(
+            PortHandle::<u32, 4>::create_with_policy(stale, RecoveryPolicy::Refuse),
+            Err(PortError::AlreadyExists)
+        ));
+        let creator = PortHandle::<u32, 4>::create(stale).unwrap();
+        creator.enqueue(1).unwrap();
+        assert_eq!(creator.segment().generation(), 1);
+    }
+
+    #[test]
+    fn test_dynamic_open_or_create() {
+        let os_id = "test_dynamic_open_or_create";
+        let _ = unlink(os_id);
+
+        let creator = DynamicPortHandle::open_or_create(os_id, 4, 16).unwrap();
+        creator.send(b"kept").unwrap();
+
+        let attacher = DynamicPortHandle::open_or_create(os_id, 4, 16).unwrap();
+        assert!(matches!(
+            DynamicPortHandle::open_or_create(os_id, 8, 16),
+            Err(PortError::LayoutMismatch)
+        ));
+
+        let mut buf = [0u8; 16];
+        assert_eq!(attacher.receive(&mut buf).map(|r| r.len), Ok(4));
+        assert_eq!(&buf[..4], b"kept");
+    }
+
+    #[test]
+    fn test_handle_moves_across_threads() {
+        let os_id = "test_handle_threads";
+        let _ = unlink(os_id);
+
+        let creator = PortHandle::<u32, 4>::create(os_id).unwrap();
+        let reader = Arc::new(PortHandle::<u32, 4>::open(os_id).unwrap());
+
+        let writer = thread::spawn(move || {
+            creator.enqueue(42).unwrap();
+            creator
+        });
+        let creator = writer.join().unwrap();
+
+        let remote = Arc::clone(&reader);
+        assert_eq!(
+            thread::spawn(move || remote.dequeue()).join().unwrap(),
+            Ok(42)
+        );
+
+        drop(creator);
+        assert!(PortHandle::<u32, 4>::open(os_id).is_err());
+    }
+
+    #[test]
+    fn test_blocking_receive_across_mappings() {
+        let os_id = "test_blocking_mappings";
+        let _ = unlink(os_id);
+
+        let destination = DynamicPortHandle::create(os_id, 2, 8).unwrap();
+        let source = DynamicPortHandle::open(os_id).unwrap();
+
+        let mut buf = [0u8; 8];
+        assert_eq!(
+            destination.receive_timeout(&mut buf, Some(Duration::from_millis(10))),
+            Err(PortError::Timeout)
+        );
+
+        let sender = thread::spawn(move || {
+            thread::sleep(Duration::from_millis(50));
+            source.send(b"wake").unwrap();
+            source
+                .send_timeout(b"later", Some(Duration::from_secs(5)))
+                .unwrap();
+        });
+
+        assert_eq!(
+            destination.receive_timeout(&mut buf, None).map(|r| r.len),
+            Ok(4)
+        );
+        assert_eq!(&buf[..4], b"wake");
+        assert_eq!(
+            destination
+                .receive_timeout(&mut buf, Some(Duration::from_secs(5)))
+                .map(|r| r.len),
+            Ok(5)
+        );
+        sender.join().unwrap();
+    }
+
+    // Three receivers block in turn with rising priorities; returns which
+    // receiver got each of the messages sent afterwards.
+    fn release_order(os_id: &str, discipline: crate::QueuingDiscipline) -> Vec<u32> {
+        use crate::PortDirection;
+
+        let _ = unlink(os_id);
+        let port = Arc::new(PortHandle::<u32, 8>::create(os_id).unwrap());
+        port.set_discipline(PortDirection::Destination, discipline);
+
+        let receivers: Vec<_> = (1..=3u32)
+            .map(|id| {
+                let receiver = Arc::clone(&port);
+                let receiver = thread::spawn(move || {
+                    crate::set_current_priority(id as u8);
+                    let value = receiver.dequeue_timeout(Some(Duration::from_secs(5)));
+                    (value.unwrap(), id)
+                });
+                while port.waiting(PortDirection::Destination) < id as usize {
+                    thread::sleep(Duration::from_millis(1));
+                }
+                receiver
+            })
+            .collect();
+
+        for value in 0..3 {
+            port.enqueue(value).unwrap();
+        }
+
+        let mut received: Vec<_> = receivers.into_iter().map(|r| r.join().unwrap()).collect();
+        received.sort();
+        received.into_iter().map(|(_, id)| id).collect()
+    }
+
+    #[test]
+    fn test_waiters_released_in_discipline_order() {
+        use crate::QueuingDiscipline;
+
+        assert_eq!(
+            release_order("test_release_fifo", QueuingDiscipline::Fifo),
+            [1, 2, 3]
+        );
+        assert_eq!(
+            release_order("test_release_priority", QueuingDiscipline::Priority),
+            [3, 2, 1]
+        );
+    }
+
+    #[test]
+    fn test_blocked_and_non_blocking_senders() {
+        const PER_SENDER: u64 = 20_000;
+
+        // Two senders block on a full port while two others retry without
+        // waiting; none may write a slot another one is writing.
+        let os_id = "test_mixed_senders";
+        let _ = unlink(os_id);
+        let port = Arc::new(PortHandle::<u64, 64>::create(os_id).unwrap());
+        let senders: Vec<_> = (0..4u64)
+            .map(|sender| {
+                let port = Arc::clone(&port);
+                thread::spawn(move || {
+                    for i in 0..PER_SENDER {
+                        let value = sender << 32 | i;
+                        if sender % 2 == 0 {
+                            port.enqueue_timeout(value, None).unwrap();
+                        } else {
+                            while port.enqueue(value).is_err() {
+                                thread::yield_now();
+                            }
+                        }
+                    }
+                })
+            })
+            .collect();
+
+        let mut last = [None; 4];
+        for _ in 0..4 * PER_SENDER {
+            let value = port.dequeue_timeout(Some(Duration::from_secs(5))).unwrap();
+            let sender = (value >> 32) as usize;
+            assert!(last[sender] < Some(value));
+            last[sender] = Some(value);
+        }
+        for sender in senders {
+            sender.join().unwrap();
+        }
+        assert_eq!(
+            last,
+            [0, 1, 2, 3].map(|sender| Some(sender << 32 | (PER_SENDER - 1)))
+        );
+        assert_eq!(port.dequeue(), Err(PortError::Empty));
+    }
+
+    #[test]
+    fn test_blocking_receive_across_processes() {
+        let os_id = "test_blocking_processes";
+        let _ = unlink(os_id);
+        let port = PortHandle::<u64, 4>::create(os_id).unwrap();
+
+        match unsafe { libc::fork() } {
+            0 => {
+                // Child: block until the parent sends, report what arrived.
+                let code = match port.dequeue_timeout(Some(Duration::from_secs(5))) {
+                    Ok(41) => 0,
+                    _ => 1,
+                };
+                unsafe { libc::_exit(code) };
+            }
+            child => {
+                thread::sleep(Duration::from_millis(50));
+                port.enqueue(41).unwrap();
+
+                let mut status = 0;
+                unsafe { libc::waitpid(child, &mut status, 0) };
+                assert!(libc::WIFEXITED(status));
+                assert_eq!(libc::WEXITSTATUS(status), 0);
+            }
+        }
+    }
+
+    #[test]
+    fn test_sampling_reads_are_never_torn() {
+        let os_id = "test_sampling_torn";
+        let _ = unlink(os_id);
+        let port = SamplingPortHandle::<64>::create(os_id, Duration::from_secs(1)).unwrap();
+        port.write_sampling_message(&[0; 64]).unwrap();
+
+        match unsafe { libc::fork() } {
+            0 => {
+                // Child: keep rewriting the message, each time with one value.
+                let writer = SamplingPortHandle::<64>::open(os_id).unwrap();
+                for value in 0..20_000u32 {
+                    let len = 1 + value as usize % 64;
+                    writer
+                        .write_sampling_message(&[value as u8; 64][..len])
+                        .unwrap();
+                }
+                unsafe { libc::_exit(0) };
+            }
+            child => {
+                let mut buf = [0u8; 64];
+                let mut status = 0;
+                while unsafe { libc::waitpid(child, &mut status, libc::WNOHANG) } == 0 {
+                    let sample = port.read_sampling_message(&mut buf).unwrap();
+                    let message = &buf[..sample.len];
+                    assert!(message.iter().all(|&byte| byte == message[0]));
+                    assert!(sample.valid);
+                }
+                assert_eq!(libc::WEXITSTATUS(status), 0);
+                assert_eq!(port.refresh_period(), Duration::from_secs(1));
+            }
+        }
+    }
+}
@@ -0,0 +1,272 @@
+use core::hint::spin_loop;
+use core::mem::{align_of, size_of};
+use core::sync::atomic::{AtomicU32, AtomicU64, Ordering};
+use core::time::Duration;
+
+use crate::error::PortError;
+use crate::futex::{current_pid, monotonic_now, process_alive};
+use crate::port::Pod;
+
+/// Marks a segment holding a port: "QPRT" in little-endian byte order.
+pub(crate) const SEGMENT_MAGIC: u32 = u32::from_le_bytes(*b"QPRT");
+
+/// Bumped whenever the layout of any port in a segment changes.
+pub(crate) const SEGMENT_VERSION: u32 = 4;
+
+// Bytes reserved for the header in front of the port, a multiple of a cache
+// line so the port behind it keeps any alignment up to one.
+pub(crate) const HEADER_SIZE: usize = 256;
+
+/// Processes other than the creator that may have a segment open at once. A
+/// process holding several handles to a segment counts once.
+pub const MAX_ATTACHERS: usize = 16;
+
+// How long an attacher waits for the creator of a fresh segment to stamp it.
+const STAMP_TIMEOUT: Duration = Duration::from_millis(100);
+
+/// What a shared segment holds: the port type, its message type and its
+/// geometry. Two processes agree on a segment only if they agree on all of it.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) struct SegmentLayout {
+    pub type_hash: u64,
+    pub capacity: u64,
+    pub slot_size: u64,
+}
+
+impl SegmentLayout {
+    /// `kind` names the port type and `T` its message type.
+    pub fn new<T: Pod>(kind: &str, capacity: usize, slot_size: usize) -> Self {
+        Self {
+            type_hash: type_hash::<T>(kind),
+            capacity: capacity as u64,
+            slot_size: slot_size as u64,
+        }
+    }
+}
+
+pub(crate) const FNV_OFFSET: u64 = 0xcbf2_9ce4_8422_2325;
+
+// Continues an FNV-1a hash over `bytes`.
+pub(crate) const fn fnv1a(mut hash: u64, bytes: &[u8]) -> u64 {
+    let mut index = 0;
+    while index < bytes.len() {
+        hash ^= bytes[index] as u64;
+        hash = hash.wrapping_mul(0x0100_0000_01b3);
+        index += 1;
+    }
+    hash
+}
+
+// FNV-1a over the port kind and the message type's tag, size and alignment.
+// Nothing the compiler chooses goes in, so separately built programs agree.
+pub(crate) fn type_hash<T: Pod>(kind: &str) -> u64 {
+    tagged_hash(kind, T::TYPE_TAG, size_of::<T>(), align_of::<T>())
+}
+
+// `type_hash` for a message type known only by its tag, size and alignment.
+pub(crate) fn tagged_hash(kind: &str, tag: u64, size: usize, align: usize) -> u64 {
+    let mut hash = fnv1a(FNV_OFFSET, kind.as_bytes());
+    hash = fnv1a(hash, &[0]);
+    for value in [tag, size as u64, align as u64] {
+        hash = fnv1a(hash, &value.to_le_bytes());
+    }
+    hash
+}
+
+// === Recovery ===
+
+/// What opening a segment does when a process that created or attached to it
+/// has died without detaching.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(
+    feature = "std",
+    derive(serde::Deserialize),
+    serde(rename_all = "lowercase")
+)]
+pub enum RecoveryPolicy {
+    /// Keep the queued messages; only release what the dead process held.
+    #[default]
+    Keep,
+    /// Also drop every queued message.
+    Reset,
+    /// Fail with [`PortError::OwnerDied`] and leave the segment untouched.
+    Refuse,
+}
+
+// === Header ===
+
+/// The header at the start of every shared segment.
+///
+/// The creator initializes the port behind it first and stamps the header
+/// last, publishing it through `magic`, so an attacher that sees the magic
+/// also sees a fully initialized port. Every process using the segment is
+/// recorded by PID, the creator in `creator` and the others in `attachers`,
+/// and each recovery from a dead process bumps `generation`. An attacher's
+/// entry holds its PID in the low half and its number of handles in the high
+/// half, so a process opening the segment again reuses its entry.
+#[repr(C)]
+pub(crate) struct SegmentHeader {
+    magic: AtomicU32,
+    version: u32,
+    type_hash: u64,
+    capacity: u64,
+    slot_size: u64,
+    creator: AtomicU32,
+    generation: AtomicU32,
+    attachers: [AtomicU64; MAX_ATTACHERS],
+}
+
+const _: () = assert!(size_of::<SegmentHeader>() <= HEADER_SIZE);
+
+// One handle in the count half of an attacher entry.
+const ONE_HANDLE: u64 = 1 << 32;
+
+fn entry_pid(entry: u64) -> u32 {
+    entry as u32
+}
+
+impl SegmentHeader {
+    /// # Safety
+    ///
+    /// Only the creator may stamp, once, before anyone attaches.
+    pub unsafe fn stamp(this: *mut SegmentHeader, layout: &SegmentLayout) {
+        let header = &mut *this;
+        header.version = SEGMENT_VERSION;
+        header.type_hash = layout.type_hash;
+        header.capacity = layout.capacity;
+        header.slot_size = layout.slot_size;
+        header.creator.store(current_pid(), Ordering::Relaxed);
+        header.magic.store(SEGMENT_MAGIC, Ordering::Release);
+    }
+
+    /// Waits for the creator to stamp the segment, then checks it is a
+    /// segment of this format holding a port of `type_hash`. Returns the
+    /// layout stamped there.
+    pub fn read(&self, type_hash: u64) -> Result<SegmentLayout, PortError> {
+        let deadline = monotonic_now() + STAMP_TIMEOUT;
+        let magic = loop {
+            let magic = self.magic.load(Ordering::Acquire);
+            if magic != 0 || monotonic_now() >= deadline {
+                break magic;
+            }
+            spin_loop();
+        };
+
+        if magic != SEGMENT_MAGIC || self.version != SEGMENT_VERSION || self.type_hash != type_hash
+        {
+            return Err(PortError::LayoutMismatch);
+        }
+        Ok(SegmentLayout {
+            type_hash,
+            capacity: self.capacity,
+            slot_size: self.slot_size,
+        })
+    }
+
+    pub fn generation(&self) -> u32 {
+        self.generation.load(Ordering::Acquire)
+    }
+
+    /// Whether the creator died without detaching. A creator that detached
+    /// from a persistent segment is simply gone.
+    pub fn creator_died(&self) -> bool {
+        let creator = self.creator.load(Ordering::Acquire);
+        creator != 0 && !process_alive(creator)
+    }
+
+    /// Whether a process recorded as using the segment has died.
+    pub fn has_dead(&self) -> bool {
+        let dead = |pid| pid != 0 && !process_alive(pid);
+        dead(self.creator.load(Ordering::Acquire))
+            || self
+                .attachers
+                .iter()
+                .any(|record| dead(entry_pid(record.load(Ordering::Acquire))))
+    }
+
+    /// Forgets every dead process and starts a new generation. The caller
+    /// has already released what they held in the port.
+    pub fn purge_dead(&self) {
+        let creator = self.creator.load(Ordering::Acquire);
+        if creator != 0 && !process_alive(creator) {
+            let _ = self
+                .creator
+                .compare_exchange(creator, 0, Ordering::AcqRel, Ordering::Relaxed);
+        }
+        for record in &self.attachers {
+            let entry = record.load(Ordering::Acquire);
+            if entry != 0 && !process_alive(entry_pid(entry)) {
+                let _ = record.compare_exchange(entry, 0, Ordering::AcqRel, Ordering::Relaxed);
+            }
+        }
+        self.generation.fetch_add(1, Ordering::AcqRel);
+    }
+
+    /// Makes the calling process the creator of a segment whose creator is
+    /// gone. Fails if another process got there first.
+    pub fn adopt(&self) -> bool {
+        self.creator
+            .compare_exchange(0, current_pid(), Ordering::AcqRel, Ordering::Relaxed)
+            .is_ok()
+    }
+
+    /// Records another handle of the calling process as attached, returning
+    /// its entry. The first handle claims a free entry; later ones count
+    /// themselves in the same entry.
+    pub fn attach(&self) -> Result<usize, PortError> {
+        let pid = current_pid();
+        'retry: loop {
+            for (index, record) in self.attachers.iter().enumerate() {
+                let entry = record.load(Ordering::Acquire);
+                if entry != 0 && entry_pid(entry) == pid {
+                    match record.compare_exchange(
+                        entry,
+                        entry + ONE_HANDLE,
+                        Ordering::AcqRel,
+                        Ordering::Relaxed,
+                    ) {
+                        Ok(_) => return Ok(index),
+                        // A handle of this process detached meanwhile.
+                        Err(_) => continue 'retry,
+                    }
+                }
+            }
+
+            let first = ONE_HANDLE | pid as u64;
+            return self
+                .attachers
+                .iter()
+                .position(|record| {
+                    record
+                        .compare_exchange(0, first, Ordering::AcqRel, Ordering::Relaxed)
+                        .is_ok()
+                })
+                .ok_or(PortError::TooManyAttachers);
+        }
+    }
+
+    /// Removes a handle of the calling process, from `attachers[entry]` or,
+    /// for `None`, as the creator. The entry is freed with the process's last
+    /// handle. A forked child holding its parent's handle leaves the parent's
+    /// record alone.
+    pub fn detach(&self, entry: Option<usize>) {
+        let pid = current_pid();
+        let Some(entry) = entry else {
+            let _ = self
+                .creator
+                .compare_exchange(pid, 0, Ordering::AcqRel, Ordering::Relaxed);
+            return;
+        };
+
+        let record = &self.attachers[entry];
+        let _ = record.fetch_update(Ordering::AcqRel, Ordering::Acquire, |entry| {
+            if entry == 0 || entry_pid(entry) != pid {
+                None
+            } else if entry >> 32 == 1 {
+                Some(0)
+            } else {
+                Some(entry - ONE_HANDLE)
+            }
+        });
+    }
+}
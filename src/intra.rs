@@ -0,0 +1,419 @@
+//! Intra-partition communication objects: buffers, blackboards and events.
+//!
+//! These live in ordinary process memory and are shared between the threads
+//! of one partition, but block and wake their callers with the same ring,
+//! futex and wait-queue machinery as the shared-memory ports.
+
+use alloc::vec;
+use alloc::vec::Vec;
+use core::ops::Deref;
+use core::sync::atomic::{AtomicBool, AtomicU32, Ordering};
+use core::time::Duration;
+
+use spin::Mutex;
+
+use crate::direction::PortDirection;
+use crate::dynamic::DynamicQueuingPort;
+use crate::error::PortError;
+use crate::futex::{deadline_after, Event};
+use crate::wait::{current_priority, QueuingDiscipline, WaitQueue};
+
+// === Buffers ===
+
+/// A bounded FIFO of up to `max_nb_message` byte messages.
+///
+/// A buffer is a [`DynamicQueuingPort`] laid out in heap memory owned by the
+/// buffer, so it offers the same blocking sends and receives, and any number
+/// of threads may send and receive at once.
+pub struct Buffer {
+    port: DynamicQueuingPort,
+    _memory: Vec<u64>,
+}
+
+impl Buffer {
+    /// Blocked senders and blocked receivers are both released in
+    /// `discipline` order.
+    pub fn new(
+        max_nb_message: usize,
+        max_message_size: usize,
+        discipline: QueuingDiscipline,
+    ) -> Result<Self, PortError> {
+        let size = DynamicQueuingPort::required_size(max_nb_message, max_message_size)
+            .ok_or(PortError::LayoutMismatch)?;
+        let mut memory = vec![0u64; size.div_ceil(8)];
+
+        let port = unsafe {
+            DynamicQueuingPort::init(
+                memory.as_mut_ptr() as *mut u8,
+                size,
+                max_nb_message,
+                max_message_size,
+            )?
+        };
+        port.set_discipline(PortDirection::Source, discipline);
+        port.set_discipline(PortDirection::Destination, discipline);
+        Ok(Self {
+            port,
+            _memory: memory,
+        })
+    }
+}
+
+impl Deref for Buffer {
+    type Target = DynamicQueuingPort;
+
+    fn deref(&self) -> &DynamicQueuingPort {
+        &self.port
+    }
+}
+
+// === Blackboards ===
+
+/// A single displayed message that readers copy without removing it.
+///
+/// Readers that find the blackboard empty may block until a message is
+/// displayed; every blocked reader then gets the message, even if the
+/// blackboard is cleared before it runs.
+pub struct Blackboard {
+    board: Mutex<Board>,
+    max_message_size: usize,
+    displayed: Event,
+    readers: WaitQueue,
+}
+
+// The last message displayed is kept after a clear, and `generation` counts
+// displays, so a blocked reader can tell one happened while it waited.
+struct Board {
+    message: Vec<u8>,
+    displayed: bool,
+    generation: u32,
+}
+
+impl Blackboard {
+    pub fn new(max_message_size: usize) -> Self {
+        Self {
+            board: Mutex::new(Board {
+                message: Vec::new(),
+                displayed: false,
+                generation: 0,
+            }),
+            max_message_size,
+            displayed: Event::new(),
+            readers: WaitQueue::new(),
+        }
+    }
+
+    pub fn max_message_size(&self) -> usize {
+        self.max_message_size
+    }
+
+    pub fn is_empty(&self) -> bool {
+        !self.board.lock().displayed
+    }
+
+    /// Number of readers currently blocked on the blackboard.
+    pub fn waiting(&self) -> usize {
+        self.readers.len()
+    }
+
+    /// Replaces the displayed message with `payload` and releases blocked
+    /// readers.
+    pub fn display(&self, payload: &[u8]) -> Result<(), PortError> {
+        if payload.len() > self.max_message_size {
+            return Err(PortError::Oversize);
+        }
+
+        {
+            let mut board = self.board.lock();
+            board.message.clear();
+            board.message.extend_from_slice(payload);
+            board.displayed = true;
+            board.generation = board.generation.wrapping_add(1);
+        }
+        self.displayed.notify();
+        Ok(())
+    }
+
+    pub fn clear(&self) {
+        self.board.lock().displayed = false;
+    }
+
+    /// Copies the displayed message into `buf`.
+    pub fn read(&self, buf: &mut [u8]) -> Result<usize, PortError> {
+        let board = self.board.lock();
+        if !board.displayed {
+            return Err(PortError::Empty);
+        }
+        Self::copy(&board, buf)
+    }
+
+    fn copy(board: &Board, buf: &mut [u8]) -> Result<usize, PortError> {
+        let message = &board.message;
+        if message.len() > buf.len() {
+            return Err(PortError::Oversize);
+        }
+        buf[..message.len()].copy_from_slice(message);
+        Ok(message.len())
+    }
+
+    /// Like [`Blackboard::read`], but waits for a message to be displayed for
+    /// up to `timeout`, or indefinitely for `None`.
+    pub fn read_timeout(
+        &self,
+        buf: &mut [u8],
+        timeout: Option<Duration>,
+    ) -> Result<usize, PortError> {
+        let deadline = timeout.map(deadline_after);
+        let seen = self.board.lock().generation;
+        self.readers.run(
+            &self.displayed,
+            current_priority(),
+            deadline.as_ref(),
+            || {
+                let board = self.board.lock();
+                !board.displayed && board.generation == seen
+            },
+            || Self::copy(&self.board.lock(), buf),
+        )?
+    }
+}
+
+// === Events ===
+
+/// An APEX event: a flag that is either up or down, with callers blocking
+/// until it goes up. A caller blocked when the event is set is released even
+/// if it is reset again before the caller runs.
+pub struct EventFlag {
+    up: AtomicBool,
+    // Counts sets, so a blocked caller can tell one happened while it waited.
+    generation: AtomicU32,
+    changed: Event,
+    waiters: WaitQueue,
+}
+
+impl EventFlag {
+    /// A new event starts down.
+    pub const fn new() -> Self {
+        Self {
+            up: AtomicBool::new(false),
+            generation: AtomicU32::new(0),
+            changed: Event::new(),
+            waiters: WaitQueue::new(),
+        }
+    }
+
+    pub fn is_up(&self) -> bool {
+        self.up.load(Ordering::Acquire)
+    }
+
+    /// Number of callers currently blocked on the event.
+    pub fn waiting(&self) -> usize {
+        self.waiters.len()
+    }
+
+    /// Puts the event up and releases every blocked caller.
+    pub fn set(&self) {
+        self.up.store(true, Ordering::Release);
+        self.generation.fetch_add(1, Ordering::AcqRel);
+        self.changed.notify();
+    }
+
+    pub fn reset(&self) {
+        self.up.store(false, Ordering::Release);
+    }
+
+    /// Waits for the event to be up for up to `timeout`, or indefinitely for
+    /// `None`.
+    pub fn wait_timeout(&self, timeout: Option<Duration>) -> Result<(), PortError> {
+        let deadline = timeout.map(deadline_after);
+        let seen = self.generation.load(Ordering::Acquire);
+        self.waiters.run(
+            &self.changed,
+            current_priority(),
+            deadline.as_ref(),
+            || !self.is_up() && self.generation.load(Ordering::Acquire) == seen,
+            || (),
+        )
+    }
+}
+
+impl Default for EventFlag {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+// === Tests ===
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+    use std::thread;
+
+    #[test]
+    fn test_buffer_blocks_when_full() {
+        let buffer = Buffer::new(2, 8, QueuingDiscipline::Fifo).unwrap();
+        assert_eq!(buffer.capacity(), 2);
+
+        buffer.send(b"one").unwrap();
+        buffer.send(b"two").unwrap();
+        assert_eq!(
+            buffer.send_timeout(b"three", Some(Duration::from_millis(5))),
+            Err(PortError::Timeout)
+        );
+
+        let mut buf = [0u8; 8];
+        assert_eq!(buffer.receive(&mut buf).map(|r| r.len), Ok(3));
+        assert_eq!(&buf[..3], b"one");
+    }
+
+    #[test]
+    fn test_buffer_many_senders() {
+        const PER_SENDER: u32 = 20_000;
+
+        let buffer =
+            Arc::new(Buffer::new(4 * PER_SENDER as usize, 8, QueuingDiscipline::Fifo).unwrap());
+        let senders: Vec<_> = (0..4u32)
+            .map(|sender| {
+                let buffer = Arc::clone(&buffer);
+                thread::spawn(move || {
+                    for i in 0..PER_SENDER {
+                        let message = [sender.to_le_bytes(), i.to_le_bytes()].concat();
+                        buffer.send(&message).unwrap();
+                    }
+                })
+            })
+            .collect();
+        for sender in senders {
+            sender.join().unwrap();
+        }
+
+        // Every message arrived once, each sender's in the order sent.
+        assert_eq!(buffer.len(), 4 * PER_SENDER as usize);
+        let mut next = [0u32; 4];
+        let mut buf = [0u8; 8];
+        while buffer.receive(&mut buf).is_ok() {
+            let sender = u32::from_le_bytes(buf[..4].try_into().unwrap()) as usize;
+            assert_eq!(
+                u32::from_le_bytes(buf[4..].try_into().unwrap()),
+                next[sender]
+            );
+            next[sender] += 1;
+        }
+        assert_eq!(next, [PER_SENDER; 4]);
+    }
+
+    #[test]
+    fn test_blackboard_releases_every_reader() {
+        let blackboard = Arc::new(Blackboard::new(8));
+        let mut buf = [0u8; 8];
+        assert_eq!(blackboard.read(&mut buf), Err(PortError::Empty));
+
+        let readers: Vec<_> = (0..3)
+            .map(|_| {
+                let blackboard = Arc::clone(&blackboard);
+                thread::spawn(move || {
+                    let mut buf = [0u8; 8];
+                    let len = blackboard
+                        .read_timeout(&mut buf, Some(Duration::from_secs(5)))
+                        .unwrap();
+                    buf[..len].to_vec()
+                })
+            })
+            .collect();
+
+        while blackboard.waiting() < 3 {
+            thread::sleep(Duration::from_millis(1));
+        }
+        blackboard.display(b"status").unwrap();
+
+        for reader in readers {
+            assert_eq!(reader.join().unwrap(), b"status");
+        }
+        assert_eq!(blackboard.read(&mut buf), Ok(6));
+
+        blackboard.clear();
+        assert_eq!(
+            blackboard.read_timeout(&mut buf, Some(Duration::from_millis(5))),
+            Err(PortError::Timeout)
+        );
+    }
+
+    #[test]
+    fn test_blackboard_pulse_releases_readers() {
+        let blackboard = Arc::new(Blackboard::new(8));
+        let readers: Vec<_> = (0..2)
+            .map(|_| {
+                let blackboard = Arc::clone(&blackboard);
+                thread::spawn(move || {
+                    let mut buf = [0u8; 8];
+                    let len = blackboard
+                        .read_timeout(&mut buf, Some(Duration::from_secs(5)))
+                        .unwrap();
+                    buf[..len].to_vec()
+                })
+            })
+            .collect();
+
+        while blackboard.waiting() < 2 {
+            thread::sleep(Duration::from_millis(1));
+        }
+        blackboard.display(b"pulse").unwrap();
+        blackboard.clear();
+
+        for reader in readers {
+            assert_eq!(reader.join().unwrap(), b"pulse");
+        }
+        assert!(blackboard.is_empty());
+    }
+
+    #[test]
+    fn test_event_set_and_reset() {
+        let event = Arc::new(EventFlag::new());
+        assert_eq!(
+            event.wait_timeout(Some(Duration::from_millis(5))),
+            Err(PortError::Timeout)
+        );
+
+        let waiter = {
+            let event = Arc::clone(&event);
+            thread::spawn(move || event.wait_timeout(Some(Duration::from_secs(5))))
+        };
+        while event.waiting() < 1 {
+            thread::sleep(Duration::from_millis(1));
+        }
+        event.set();
+        assert_eq!(waiter.join().unwrap(), Ok(()));
+
+        // An event stays up until reset.
+        assert_eq!(event.wait_timeout(None), Ok(()));
+        event.reset();
+        assert!(!event.is_up());
+    }
+
+    #[test]
+    fn test_event_pulse_releases_waiters() {
+        let event = Arc::new(EventFlag::new());
+        let waiters: Vec<_> = (0..2)
+            .map(|_| {
+                let event = Arc::clone(&event);
+                thread::spawn(move || event.wait_timeout(Some(Duration::from_secs(5))))
+            })
+            .collect();
+
+        while event.waiting() < 2 {
+            thread::sleep(Duration::from_millis(1));
+        }
+        event.set();
+        event.reset();
+
+        for waiter in waiters {
+            assert_eq!(waiter.join().unwrap(), Ok(()));
+        }
+        assert_eq!(
+            event.wait_timeout(Some(Duration::from_millis(5))),
+            Err(PortError::Timeout)
+        );
+    }
+}
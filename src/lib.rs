@@ -0,0 +1,42 @@
+#![cfg_attr(not(any(test, feature = "std")), no_std)]
+
+extern crate alloc;
+
+#[cfg(feature = "std")]
+pub mod apex;
+#[cfg(feature = "std")]
+pub mod codegen;
+#[cfg(feature = "std")]
+pub mod config;
+mod direction;
+mod dynamic;
+mod error;
+mod futex;
+mod handle;
+mod header;
+mod intra;
+mod mpmc;
+mod multicast;
+mod port;
+mod ring;
+mod sampling;
+mod shared;
+mod wait;
+
+pub use direction::{
+    Destination, DestinationPort, DirectedPort, Direction, DynamicDestinationPort,
+    DynamicSourcePort, PortDirection, Source, SourcePort,
+};
+pub use dynamic::DynamicQueuingPort;
+pub use error::PortError;
+pub use handle::{DynamicPortHandle, MpmcPortHandle, PortHandle, SamplingPortHandle};
+pub use header::{RecoveryPolicy, MAX_ATTACHERS};
+pub use intra::{Blackboard, Buffer, EventFlag};
+pub use mpmc::MpmcQueuingPort;
+pub use multicast::{Delivery, MulticastSourcePort};
+pub use port::{type_tag, Message, MessagePort, Pod, QueuingPort, Received, MSG_COUNT};
+pub use sampling::{Sample, SamplingPort};
+pub use shared::{release_shared, unlink, Lifetime, Segment};
+#[cfg(feature = "std")]
+pub use wait::set_current_priority;
+pub use wait::{current_priority, Priority, QueuingDiscipline, MAX_WAITERS};
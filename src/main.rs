@@ -1,182 +1,167 @@
-#![cfg_attr(not(test), no_std)]
+#![cfg_attr(not(feature = "std"), no_std)]
 
-use core::sync::atomic::{AtomicUsize, Ordering};
-use core::mem::size_of;
+#[allow(unused_imports)]
+use master_project_queuing_port::QueuingPort;
 
-#[cfg(test)]
-extern crate std;
-
-#[cfg(test)]
-use std::thread;
+// === Main Function ===
 
-use shared_memory::{Shmem, ShmemConf};
+#[cfg(feature = "std")]
+fn main() {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    match args.as_slice() {
+        [] => demo(),
+        [command, path] if command == "validate" => std::process::exit(validate(path)),
+        [command, path] if command == "serve" => std::process::exit(serve(path)),
+        _ => {
+            eprintln!("usage: master-project-queuing-port [validate|serve <config.toml>]");
+            std::process::exit(2);
+        }
+    }
+}
 
-const MSG_COUNT: usize = 16;
-const MSG_SIZE: usize = size_of::<i32>();
+#[cfg(feature = "std")]
+fn demo() {
+    let os_id = "main_queue";
 
-#[repr(C)]
-pub struct QueuingPort {
-    buffer: [u8; MSG_COUNT * MSG_SIZE],
-    write_index: AtomicUsize,
-    read_index: AtomicUsize,
+    QueuingPort::<i32>::enqueue_shared(100, os_id).unwrap();
+    QueuingPort::<i32>::enqueue_shared(200, os_id).unwrap();
+    QueuingPort::<i32>::enqueue_shared(300, os_id).unwrap();
+
+    println!(
+        "Dequeued: {:?}",
+        QueuingPort::<i32>::dequeue_shared(os_id).unwrap()
+    );
+    println!(
+        "Dequeued: {:?}",
+        QueuingPort::<i32>::dequeue_shared(os_id).unwrap()
+    );
+    println!(
+        "Dequeued: {:?}",
+        QueuingPort::<i32>::dequeue_shared(os_id).unwrap()
+    );
 }
 
-unsafe impl Sync for QueuingPort {}
-
-impl QueuingPort {
-    pub fn new() -> Self {
-        Self {
-            buffer: [0; MSG_COUNT * MSG_SIZE],
-            write_index: AtomicUsize::new(0),
-            read_index: AtomicUsize::new(0),
+// Checks the configuration at `path` and prints one line per finding as
+// `file:line:column: severity[code]: message`. Returns the exit status: 0 if
+// the configuration is usable, 1 if it has errors, 2 if it cannot be read.
+#[cfg(feature = "std")]
+fn validate(path: &str) -> i32 {
+    use master_project_queuing_port::config::{diagnose, Severity};
+
+    let text = match std::fs::read_to_string(path) {
+        Ok(text) => text,
+        Err(error) => {
+            println!("{path}:1:1: error[read]: {error}");
+            return 2;
         }
+    };
+
+    let diagnostics = diagnose(&text);
+    for diagnostic in &diagnostics {
+        let (line, column) = diagnostic.line_column(&text);
+        println!(
+            "{path}:{line}:{column}: {}[{}]: {}",
+            diagnostic.severity, diagnostic.code, diagnostic.message
+        );
     }
 
-    pub fn enqueue(&self, item: i32) -> Result<(), &'static str> {
-        let write = self.write_index.load(Ordering::Relaxed);
-        let next = (write + 1) % MSG_COUNT;
-
-        if next == self.read_index.load(Ordering::Acquire) {
-            return Err("Queue full");
-        }
+    let failed = diagnostics
+        .iter()
+        .any(|diagnostic| diagnostic.severity == Severity::Error);
+    i32::from(failed)
+}
 
-        let offset = write * MSG_SIZE;
-        let ptr = &self.buffer[offset] as *const u8 as *mut i32;
-        unsafe { ptr.write(item); }
+// === Port Manager ===
 
-        self.write_index.store(next, Ordering::Release);
-        Ok(())
-    }
+#[cfg(feature = "std")]
+mod signals {
+    use std::sync::atomic::{AtomicBool, Ordering};
 
-    pub fn dequeue(&self) -> Result<i32, &'static str> {
-        let read = self.read_index.load(Ordering::Relaxed);
+    pub static STOP: AtomicBool = AtomicBool::new(false);
+    pub static REPORT: AtomicBool = AtomicBool::new(false);
 
-        if read == self.write_index.load(Ordering::Acquire) {
-            return Err("Queue empty");
+    extern "C" fn on_signal(signal: libc::c_int) {
+        match signal {
+            libc::SIGUSR1 => REPORT.store(true, Ordering::Relaxed),
+            _ => STOP.store(true, Ordering::Relaxed),
         }
-
-        let offset = read * MSG_SIZE;
-        let ptr = &self.buffer[offset] as *const u8 as *const i32;
-        let value = unsafe { ptr.read() };
-
-        self.read_index.store((read + 1) % MSG_COUNT, Ordering::Release);
-        Ok(value)
     }
-}
-
-// === Shared Memory Setup ===
-
-static mut SHARED_QUEUE_PTR: *mut QueuingPort = core::ptr::null_mut();
-static mut SHMEM_HANDLE: Option<Shmem> = None;
 
-fn get_shared_queue(os_id: &str) -> &'static mut QueuingPort {
-    unsafe {
-        if !SHARED_QUEUE_PTR.is_null() {
-            return &mut *SHARED_QUEUE_PTR;
+    pub fn install() {
+        let handler = on_signal as extern "C" fn(libc::c_int) as libc::sighandler_t;
+        for signal in [libc::SIGINT, libc::SIGTERM, libc::SIGUSR1] {
+            unsafe {
+                libc::signal(signal, handler);
+            }
         }
-
-        let size = size_of::<QueuingPort>();
-        let shmem = ShmemConf::new()
-            .size(size)
-            .os_id(os_id)
-            .create()
-            .expect("Failed to create shared memory");
-
-        let ptr = shmem.as_ptr() as *mut QueuingPort;
-        ptr.write(QueuingPort::new());
-
-        SHMEM_HANDLE = Some(shmem);
-        SHARED_QUEUE_PTR = ptr;
-
-        &mut *ptr
     }
 }
 
-// === Public API ===
-
-impl QueuingPort {
-    pub fn enqueue_shared(item: i32, os_id: &str) -> Result<(), &'static str> {
-        get_shared_queue(os_id).enqueue(item)
+// Runs the port manager: creates every port segment the configuration at
+// `path` declares, taking over any left behind by a crashed manager as the
+// configured recovery policy allows, keeps them alive until SIGINT or
+// SIGTERM, and removes them on the way out. SIGUSR1 prints the status of
+// every segment. Returns the exit status.
+#[cfg(feature = "std")]
+fn serve(path: &str) -> i32 {
+    use master_project_queuing_port::config::SystemConfig;
+    use std::sync::atomic::Ordering;
+    use std::time::Duration;
+
+    let config = match SystemConfig::load(path) {
+        Ok(config) => config,
+        Err(error) => {
+            eprintln!("{path}: {error}");
+            return 2;
+        }
+    };
+    let segments = match config.create_ports() {
+        Ok(segments) => segments,
+        Err(error) => {
+            eprintln!("cannot create port segments: {error}");
+            return 1;
+        }
+    };
+
+    signals::install();
+    println!(
+        "managing {} port segments (pid {})",
+        segments.len(),
+        std::process::id()
+    );
+    report(&segments);
+
+    while !signals::STOP.load(Ordering::Relaxed) {
+        if signals::REPORT.swap(false, Ordering::Relaxed) {
+            report(&segments);
+        }
+        std::thread::sleep(Duration::from_millis(100));
     }
 
-    pub fn dequeue_shared(os_id: &str) -> Result<i32, &'static str> {
-        get_shared_queue(os_id).dequeue()
-    }
+    println!("removing {} port segments", segments.len());
+    0
 }
 
-// === Main Function ===
-
 #[cfg(feature = "std")]
-fn main() {
-    let os_id = "main_queue";
-
-    QueuingPort::enqueue_shared(100, os_id).unwrap();
-    QueuingPort::enqueue_shared(200, os_id).unwrap();
-    QueuingPort::enqueue_shared(300, os_id).unwrap();
-
-    println!("Dequeued: {:?}", QueuingPort::dequeue_shared(os_id).unwrap());
-    println!("Dequeued: {:?}", QueuingPort::dequeue_shared(os_id).unwrap());
-    println!("Dequeued: {:?}", QueuingPort::dequeue_shared(os_id).unwrap());
+fn report(
+    segments: &std::collections::BTreeMap<String, master_project_queuing_port::DynamicPortHandle>,
+) {
+    use master_project_queuing_port::PortDirection;
+
+    for (name, port) in segments {
+        println!(
+            "{name}: {}/{} messages of at most {} bytes, overflow {}, waiting senders {}, receivers {}",
+            port.len(),
+            port.capacity(),
+            port.max_message_size(),
+            if port.overflowed() { "yes" } else { "no" },
+            port.waiting(PortDirection::Source),
+            port.waiting(PortDirection::Destination)
+        );
+    }
 }
 
 #[cfg(not(feature = "std"))]
 fn main() {
     // no-op for embedded/no_std
 }
-
-// === Tests ===
-
-#[cfg(test)]
-mod tests {
-    use super::*;
-
-    #[test]
-    fn test_basic_enqueue_dequeue_shared() {
-        let os_id = "test_queue_1";
-
-        QueuingPort::enqueue_shared(10, os_id).unwrap();
-        QueuingPort::enqueue_shared(20, os_id).unwrap();
-
-        let x = QueuingPort::dequeue_shared(os_id).unwrap();
-        let y = QueuingPort::dequeue_shared(os_id).unwrap();
-
-        println!("Dequeued values: {}, {}", x, y);
-        assert_eq!(x, 10);
-        assert_eq!(y, 20);
-    }
-
-    #[test]
-    fn test_single_writer_reader() {
-    let os_id = "test_queue_fixed";
-
-    //writer thread
-    let writer = thread::spawn({
-        let id = os_id.to_string();
-        move || {
-            for i in 0..10 {
-                let _ = QueuingPort::enqueue_shared(i, &id);
-            }
-        }
-    });
-
-    writer.join().unwrap();
-
-    // reader thread
-    let reader = thread::spawn({
-        let id = os_id.to_string();
-        move || {
-            let mut results = vec![];
-            for _ in 0..10 {
-                if let Ok(val) = QueuingPort::dequeue_shared(&id) {
-                    results.push(val);
-                }
-            }
-
-            results.sort();
-            assert_eq!(results, (0..10).collect::<Vec<_>>());
-        }
-    });
-
-    reader.join().unwrap();
-}
-}
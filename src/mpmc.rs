@@ -0,0 +1,371 @@
+use core::cell::UnsafeCell;
+use core::mem::{size_of, MaybeUninit};
+use core::sync::atomic::{AtomicU32, AtomicUsize, Ordering};
+
+use crate::error::PortError;
+use crate::port::{Message, Pod, Received, MSG_COUNT};
+
+// A slot and its sequence number. The slot at `pos % N` is free for the
+// producer claiming position `pos` when its sequence is `pos`, and holds the
+// message for the consumer claiming `pos` when it is `pos + 1`. The sequence
+// is stored minus the slot's index, so zero-filled memory is an empty port.
+#[repr(C)]
+struct Slot<T> {
+    sequence: AtomicUsize,
+    value: UnsafeCell<MaybeUninit<T>>,
+}
+
+/// A ring of `N` slots, each holding one `T`, that any number of threads or
+/// processes may send to and receive from at once.
+///
+/// Producers and consumers each claim a position with a compare-and-swap, and
+/// the slot's sequence number hands it from one to the next, so two writers
+/// never share a slot. All `N` slots hold messages. Callers never block: a
+/// full port turns the message away and raises the overflow flag.
+///
+/// Positions count up and wrap at `usize::MAX`, so `N` must be a power of two
+/// for the position after the wrap to land on the next slot:
+///
+/// ```compile_fail
+/// use master_project_queuing_port::MpmcQueuingPort;
+///
+/// let port = MpmcQueuingPort::<u32, 3>::new();
+/// ```
+#[repr(C)]
+pub struct MpmcQueuingPort<T: Pod, const N: usize = MSG_COUNT> {
+    slots: [Slot<T>; N],
+    enqueue_pos: AtomicUsize,
+    dequeue_pos: AtomicUsize,
+    overflow: AtomicU32,
+}
+
+unsafe impl<T: Pod, const N: usize> Sync for MpmcQueuingPort<T, N> {}
+
+impl<T: Pod, const N: usize> Default for MpmcQueuingPort<T, N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T: Pod, const N: usize> MpmcQueuingPort<T, N> {
+    pub const CAPACITY: usize = N;
+    pub const SLOT_SIZE: usize = size_of::<T>();
+
+    pub(crate) const GEOMETRY_OK: () = {
+        assert!(N >= 1, "a queuing port needs at least 1 slot");
+        assert!(
+            N.is_power_of_two(),
+            "an MPMC queuing port needs a power-of-two slot count"
+        );
+        assert!(
+            size_of::<T>() > 0,
+            "a queuing port cannot carry zero-sized messages"
+        );
+    };
+
+    pub fn new() -> Self {
+        let () = Self::GEOMETRY_OK;
+
+        Self {
+            slots: core::array::from_fn(|_| Slot {
+                sequence: AtomicUsize::new(0),
+                value: UnsafeCell::new(MaybeUninit::uninit()),
+            }),
+            enqueue_pos: AtomicUsize::new(0),
+            dequeue_pos: AtomicUsize::new(0),
+            overflow: AtomicU32::new(0),
+        }
+    }
+
+    fn sequence(&self, index: usize) -> usize {
+        self.slots[index]
+            .sequence
+            .load(Ordering::Acquire)
+            .wrapping_add(index)
+    }
+
+    fn set_sequence(&self, index: usize, sequence: usize) {
+        self.slots[index]
+            .sequence
+            .store(sequence.wrapping_sub(index), Ordering::Release);
+    }
+
+    pub fn capacity(&self) -> usize {
+        Self::CAPACITY
+    }
+
+    /// Messages queued or being written; only a snapshot while other callers
+    /// are active.
+    pub fn len(&self) -> usize {
+        // The read position never passes the write position, so reading it
+        // first keeps the difference from going negative.
+        let dequeue_pos = self.dequeue_pos.load(Ordering::Acquire);
+        let enqueue_pos = self.enqueue_pos.load(Ordering::Acquire);
+        enqueue_pos.wrapping_sub(dequeue_pos).min(N)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Drops every message queued so far. Any caller may clear the port.
+    pub fn clear(&self) {
+        while self.dequeue().is_ok() {}
+    }
+
+    pub fn enqueue(&self, item: T) -> Result<(), PortError> {
+        let mut pos = self.enqueue_pos.load(Ordering::Relaxed);
+        loop {
+            let index = pos % N;
+            let lag = self.sequence(index).wrapping_sub(pos) as isize;
+
+            if lag == 0 {
+                match self.enqueue_pos.compare_exchange_weak(
+                    pos,
+                    pos.wrapping_add(1),
+                    Ordering::Relaxed,
+                    Ordering::Relaxed,
+                ) {
+                    Ok(_) => {
+                        unsafe { (*self.slots[index].value.get()).write(item) };
+                        self.set_sequence(index, pos.wrapping_add(1));
+                        return Ok(());
+                    }
+                    Err(current) => pos = current,
+                }
+            } else if lag < 0 {
+                // The slot still holds the message from the previous lap.
+                self.overflow.store(1, Ordering::Release);
+                return Err(PortError::Full);
+            } else {
+                // Another producer claimed `pos` and moved on.
+                pos = self.enqueue_pos.load(Ordering::Relaxed);
+            }
+        }
+    }
+
+    pub fn dequeue(&self) -> Result<T, PortError> {
+        let mut pos = self.dequeue_pos.load(Ordering::Relaxed);
+        loop {
+            let index = pos % N;
+            let lag = self.sequence(index).wrapping_sub(pos.wrapping_add(1)) as isize;
+
+            if lag == 0 {
+                match self.dequeue_pos.compare_exchange_weak(
+                    pos,
+                    pos.wrapping_add(1),
+                    Ordering::Relaxed,
+                    Ordering::Relaxed,
+                ) {
+                    Ok(_) => {
+                        let item = unsafe { (*self.slots[index].value.get()).assume_init_read() };
+                        self.set_sequence(index, pos.wrapping_add(N));
+                        return Ok(item);
+                    }
+                    Err(current) => pos = current,
+                }
+            } else if lag < 0 {
+                // Nothing written at `pos` yet.
+                return Err(PortError::Empty);
+            } else {
+                // Another consumer claimed `pos` and moved on.
+                pos = self.dequeue_pos.load(Ordering::Relaxed);
+            }
+        }
+    }
+
+    /// Whether a message was turned away because the port was full since the
+    /// overflow was last reported.
+    pub fn overflowed(&self) -> bool {
+        self.overflow.load(Ordering::Acquire) != 0
+    }
+
+    /// Reports and clears the overflow flag.
+    pub fn take_overflow(&self) -> bool {
+        self.overflow.swap(0, Ordering::AcqRel) != 0
+    }
+
+    // A process that died between claiming a slot and handing it on leaves
+    // the port stuck at that slot, and only a reset frees it: every slot and
+    // position goes back to zero, dropping the queued messages. Callers
+    // active meanwhile may lose their message.
+    pub(crate) fn recover(&self, reset: bool) {
+        if reset {
+            for slot in &self.slots {
+                slot.sequence.store(0, Ordering::Release);
+            }
+            self.enqueue_pos.store(0, Ordering::Release);
+            self.dequeue_pos.store(0, Ordering::Release);
+            self.overflow.store(0, Ordering::Release);
+        }
+    }
+}
+
+// === Variable-Length Messages ===
+
+impl<const M: usize, const N: usize> MpmcQueuingPort<Message<M>, N> {
+    pub fn send(&self, payload: &[u8]) -> Result<(), PortError> {
+        self.enqueue(Message::new(payload)?)
+    }
+
+    /// Unlike [`QueuingPort::receive`](crate::QueuingPort::receive), a
+    /// message too long for `buf` is taken off the port before the check and
+    /// lost, since another consumer may take its slot meanwhile.
+    pub fn receive(&self, buf: &mut [u8]) -> Result<Received, PortError> {
+        let message = self.dequeue()?;
+        let payload = message.as_bytes();
+        if payload.len() > buf.len() {
+            return Err(PortError::Oversize);
+        }
+
+        buf[..payload.len()].copy_from_slice(payload);
+        Ok(Received {
+            len: payload.len(),
+            overflow: self.take_overflow(),
+        })
+    }
+}
+
+// === Tests ===
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::handle::MpmcPortHandle;
+    use crate::shared::unlink;
+    use std::sync::atomic::AtomicBool;
+    use std::thread;
+    use std::vec::Vec;
+
+    #[test]
+    fn test_mpmc_full_capacity() {
+        let port = MpmcQueuingPort::<u32, 4>::new();
+
+        // Several laps, so every slot is reused.
+        for lap in 0..3 {
+            for i in 0..4 {
+                port.enqueue(lap * 4 + i).unwrap();
+            }
+            assert_eq!(port.len(), 4);
+            assert_eq!(port.enqueue(99), Err(PortError::Full));
+            assert!(port.take_overflow());
+            for i in 0..4 {
+                assert_eq!(port.dequeue(), Ok(lap * 4 + i));
+            }
+            assert_eq!(port.dequeue(), Err(PortError::Empty));
+        }
+
+        let messages = MpmcQueuingPort::<Message<4>, 2>::new();
+        let mut buf = [0u8; 4];
+        messages.send(b"ab").unwrap();
+        assert_eq!(messages.send(b"too long"), Err(PortError::Oversize));
+        assert_eq!(
+            messages.receive(&mut buf),
+            Ok(Received {
+                len: 2,
+                overflow: false
+            })
+        );
+        assert_eq!(&buf[..2], b"ab");
+    }
+
+    #[test]
+    fn test_mpmc_positions_wrap() {
+        // Start one lap short of the wrap, with every slot free for its
+        // position as a fresh port's would be.
+        let port = MpmcQueuingPort::<u32, 4>::new();
+        let start = 0usize.wrapping_sub(4);
+        for slot in &port.slots {
+            slot.sequence.store(start, Ordering::Relaxed);
+        }
+        port.enqueue_pos.store(start, Ordering::Relaxed);
+        port.dequeue_pos.store(start, Ordering::Relaxed);
+
+        for lap in 0..3 {
+            for i in 0..3 {
+                port.enqueue(lap * 3 + i).unwrap();
+            }
+            assert_eq!(port.len(), 3);
+            for i in 0..3 {
+                assert_eq!(port.dequeue(), Ok(lap * 3 + i));
+            }
+            assert_eq!(port.dequeue(), Err(PortError::Empty));
+        }
+    }
+
+    #[test]
+    fn test_mpmc_stress() {
+        const PRODUCERS: usize = 4;
+        const CONSUMERS: usize = 4;
+        const PER_PRODUCER: u64 = 20_000;
+
+        // Every thread maps the segment on its own, as separate processes
+        // would, and a small ring keeps them all contending for slots.
+        let os_id = "test_mpmc_stress";
+        let _ = unlink(os_id);
+        let _creator = MpmcPortHandle::<u64, 8>::create(os_id).unwrap();
+        let done = AtomicBool::new(false);
+
+        let received: Vec<Vec<u64>> = thread::scope(|scope| {
+            let producers: Vec<_> = (0..PRODUCERS as u64)
+                .map(|producer| {
+                    scope.spawn(move || {
+                        let port = MpmcPortHandle::<u64, 8>::open(os_id).unwrap();
+                        for i in 0..PER_PRODUCER {
+                            while port.enqueue(producer << 32 | i).is_err() {
+                                thread::yield_now();
+                            }
+                        }
+                    })
+                })
+                .collect();
+            let consumers: Vec<_> = (0..CONSUMERS)
+                .map(|_| {
+                    scope.spawn(|| {
+                        let port = MpmcPortHandle::<u64, 8>::open(os_id).unwrap();
+                        let mut values = Vec::new();
+                        loop {
+                            match port.dequeue() {
+                                Ok(value) => values.push(value),
+                                Err(_) if done.load(Ordering::Acquire) => {
+                                    // Anything pushed before `done` is queued.
+                                    if port.is_empty() {
+                                        break values;
+                                    }
+                                }
+                                Err(_) => thread::yield_now(),
+                            }
+                        }
+                    })
+                })
+                .collect();
+
+            for producer in producers {
+                producer.join().unwrap();
+            }
+            done.store(true, Ordering::Release);
+            consumers
+                .into_iter()
+                .map(|consumer| consumer.join().unwrap())
+                .collect()
+        });
+
+        // Each consumer sees every producer's messages in the order sent.
+        for values in &received {
+            let mut last = [None; PRODUCERS];
+            for &value in values {
+                let producer = (value >> 32) as usize;
+                assert!(last[producer] < Some(value));
+                last[producer] = Some(value);
+            }
+        }
+
+        // And together they saw each message exactly once.
+        let mut all: Vec<u64> = received.into_iter().flatten().collect();
+        all.sort_unstable();
+        let expected: Vec<u64> = (0..PRODUCERS as u64)
+            .flat_map(|producer| (0..PER_PRODUCER).map(move |i| producer << 32 | i))
+            .collect();
+        assert_eq!(all, expected);
+    }
+}
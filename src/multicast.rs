@@ -0,0 +1,188 @@
+use alloc::vec::Vec;
+use core::time::Duration;
+
+use crate::direction::DynamicSourcePort;
+use crate::error::PortError;
+use crate::futex::monotonic_now;
+
+/// How many destinations a multicast send reached.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Delivery {
+    /// Destinations the message was queued on.
+    pub delivered: usize,
+    /// Full destinations that lost the message; each one reports the
+    /// overflow on its next receive.
+    pub overflowed: usize,
+}
+
+/// The source end of a multicast channel.
+///
+/// Every destination keeps its own queue, and this port holds the source end
+/// of each. A send appends the message to every queue with room; a full
+/// destination loses the message and records the overflow, but never holds
+/// up delivery to the others.
+pub struct MulticastSourcePort {
+    destinations: Vec<DynamicSourcePort>,
+}
+
+impl MulticastSourcePort {
+    /// All destination queues must carry messages of the same maximum size.
+    pub fn new(destinations: Vec<DynamicSourcePort>) -> Result<Self, PortError> {
+        if let Some(first) = destinations.first() {
+            let size = first.max_message_size();
+            if destinations
+                .iter()
+                .any(|port| port.max_message_size() != size)
+            {
+                return Err(PortError::LayoutMismatch);
+            }
+        }
+        Ok(Self { destinations })
+    }
+
+    pub fn destinations(&self) -> &[DynamicSourcePort] {
+        &self.destinations
+    }
+
+    /// Largest message the channel carries, or 0 without destinations.
+    pub fn max_message_size(&self) -> usize {
+        self.destinations
+            .first()
+            .map_or(0, DynamicSourcePort::max_message_size)
+    }
+
+    /// Appends `payload` to every destination queue without blocking.
+    pub fn send(&self, payload: &[u8]) -> Result<Delivery, PortError> {
+        self.check(payload)?;
+
+        let mut delivery = Delivery::default();
+        for port in &self.destinations {
+            match port.send(payload) {
+                Ok(()) => delivery.delivered += 1,
+                Err(PortError::Full) => delivery.overflowed += 1,
+                Err(error) => return Err(error),
+            }
+        }
+        Ok(delivery)
+    }
+
+    /// Like [`MulticastSourcePort::send`], but waits up to `timeout`, or
+    /// indefinitely for `None`, for full destinations to make room. Queues
+    /// with room get the message first, so a slow destination only delays
+    /// itself.
+    pub fn send_timeout(
+        &self,
+        payload: &[u8],
+        timeout: Option<Duration>,
+    ) -> Result<Delivery, PortError> {
+        self.check(payload)?;
+        let deadline = timeout.map(|timeout| monotonic_now() + timeout);
+
+        // This port is the only writer of each queue, so a queue seen with
+        // room cannot fill up before the send below.
+        let mut delivery = Delivery::default();
+        let mut full = Vec::new();
+        for port in &self.destinations {
+            if port.len() < port.capacity() {
+                port.send(payload)?;
+                delivery.delivered += 1;
+            } else {
+                full.push(port);
+            }
+        }
+
+        for port in full {
+            let remaining = deadline.map(|deadline| deadline.saturating_sub(monotonic_now()));
+            match port.send_timeout(payload, remaining) {
+                Ok(()) => delivery.delivered += 1,
+                Err(PortError::Timeout) => delivery.overflowed += 1,
+                Err(error) => return Err(error),
+            }
+        }
+        Ok(delivery)
+    }
+
+    fn check(&self, payload: &[u8]) -> Result<(), PortError> {
+        if payload.len() > self.max_message_size() {
+            return Err(PortError::Oversize);
+        }
+        Ok(())
+    }
+}
+
+// === Tests ===
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::handle::DynamicPortHandle;
+    use crate::shared::unlink;
+
+    #[test]
+    fn test_multicast_full_destination_does_not_block_others() {
+        let ids = ["test_multicast_a", "test_multicast_b", "test_multicast_c"];
+        let destinations: Vec<_> = ids
+            .iter()
+            .map(|id| {
+                let _ = unlink(id);
+                DynamicPortHandle::create(id, 2, 8)
+                    .unwrap()
+                    .into_destination()
+            })
+            .collect();
+        let source = MulticastSourcePort::new(
+            ids.iter()
+                .map(|id| DynamicPortHandle::open(id).unwrap().into_source())
+                .collect(),
+        )
+        .unwrap();
+        let mut buf = [0u8; 8];
+
+        assert_eq!(source.send(b"too long!"), Err(PortError::Oversize));
+        assert_eq!(
+            source.send(b"one"),
+            Ok(Delivery {
+                delivered: 3,
+                overflowed: 0
+            })
+        );
+
+        // Destination `a` drains its queue; `b` and `c` fall behind and fill.
+        destinations[0].receive(&mut buf).unwrap();
+        source.send(b"two").unwrap();
+        destinations[0].receive(&mut buf).unwrap();
+        assert_eq!(
+            source.send(b"three"),
+            Ok(Delivery {
+                delivered: 1,
+                overflowed: 2
+            })
+        );
+        assert_eq!(
+            source.send_timeout(b"four", Some(Duration::from_millis(5))),
+            Ok(Delivery {
+                delivered: 1,
+                overflowed: 2
+            })
+        );
+
+        let received = destinations[0].receive(&mut buf).unwrap();
+        assert_eq!(
+            (&buf[..received.len], received.overflow),
+            (&b"three"[..], false)
+        );
+        for destination in &destinations[1..] {
+            let received = destination.receive(&mut buf).unwrap();
+            assert_eq!(
+                (&buf[..received.len], received.overflow),
+                (&b"one"[..], true)
+            );
+            let received = destination.receive(&mut buf).unwrap();
+            assert_eq!(
+                (&buf[..received.len], received.overflow),
+                (&b"two"[..], false)
+            );
+            assert_eq!(destination.receive(&mut buf), Err(PortError::Empty));
+        }
+    }
+}
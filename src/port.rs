@@ -0,0 +1,450 @@
+use core::cell::UnsafeCell;
+use core::mem::{size_of, MaybeUninit};
+use core::time::Duration;
+
+use crate::direction::PortDirection;
+use crate::error::PortError;
+use crate::futex::deadline_after;
+use crate::header::{fnv1a, FNV_OFFSET};
+use crate::ring::Ring;
+use crate::wait::QueuingDiscipline;
+
+pub const MSG_COUNT: usize = 16;
+
+/// Marker for message types that may be copied byte-for-byte into a port.
+///
+/// # Safety
+///
+/// Implementors must be `#[repr(C)]` (or a primitive), contain no pointers or
+/// references, and be valid for any bit pattern another process may write.
+pub unsafe trait Pod: Copy + 'static {
+    /// Identifies the message type in segment headers, together with its size
+    /// and alignment, so separately built programs agree on it. Types that
+    /// may meet in one segment need distinct tags; [`type_tag`] makes one
+    /// from a name.
+    const TYPE_TAG: u64;
+}
+
+/// A [`Pod::TYPE_TAG`] derived from `name`.
+pub const fn type_tag(name: &str) -> u64 {
+    fnv1a(FNV_OFFSET, name.as_bytes())
+}
+
+macro_rules! impl_pod {
+    ($($t:ty),*) => {
+        $(unsafe impl Pod for $t {
+            const TYPE_TAG: u64 = type_tag(stringify!($t));
+        })*
+    };
+}
+
+impl_pod!(u8, u16, u32, u64, u128, usize, i8, i16, i32, i64, i128, isize, f32, f64);
+
+unsafe impl<T: Pod, const N: usize> Pod for [T; N] {
+    const TYPE_TAG: u64 = fnv1a(T::TYPE_TAG, &(N as u64).to_le_bytes());
+}
+
+/// A ring of `N` slots, each holding one `T`. The port holds up to `N`
+/// messages.
+#[repr(C)]
+pub struct QueuingPort<T: Pod, const N: usize = MSG_COUNT> {
+    buffer: UnsafeCell<[MaybeUninit<T>; N]>,
+    ring: Ring,
+}
+
+// Callers on one end of the ring take turns, so a slot is only ever written
+// by the writer holding the turn or read by the reader holding the other.
+unsafe impl<T: Pod, const N: usize> Sync for QueuingPort<T, N> {}
+
+impl<T: Pod, const N: usize> Default for QueuingPort<T, N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T: Pod, const N: usize> QueuingPort<T, N> {
+    pub const CAPACITY: usize = N;
+    pub const SLOT_SIZE: usize = size_of::<T>();
+
+    pub(crate) const GEOMETRY_OK: () = {
+        assert!(N >= 1, "a queuing port needs at least 1 slot");
+        assert!(
+            size_of::<T>() > 0,
+            "a queuing port cannot carry zero-sized messages"
+        );
+    };
+
+    pub fn new() -> Self {
+        let () = Self::GEOMETRY_OK;
+
+        Self {
+            buffer: UnsafeCell::new([MaybeUninit::uninit(); N]),
+            ring: Ring::new(),
+        }
+    }
+
+    /// A port releasing blocked callers on both ends in `discipline` order.
+    pub fn with_discipline(discipline: QueuingDiscipline) -> Self {
+        let port = Self::new();
+        port.set_discipline(PortDirection::Source, discipline);
+        port.set_discipline(PortDirection::Destination, discipline);
+        port
+    }
+
+    fn slot(&self, index: usize) -> *mut T {
+        unsafe { (self.buffer.get() as *mut T).add(index) }
+    }
+
+    pub fn capacity(&self) -> usize {
+        Self::CAPACITY
+    }
+
+    pub fn len(&self) -> usize {
+        self.ring.len(N)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn clear(&self) {
+        self.ring.clear();
+    }
+
+    pub fn enqueue(&self, item: T) -> Result<(), PortError> {
+        self.ring
+            .push(N, |index| unsafe { self.slot(index).write(item) })
+    }
+
+    pub fn dequeue(&self) -> Result<T, PortError> {
+        self.dequeue_with(|item| Ok(*item))
+    }
+
+    /// Like [`QueuingPort::enqueue`], but waits for a free slot for up to
+    /// `timeout`, or indefinitely for `None`.
+    pub fn enqueue_timeout(&self, item: T, timeout: Option<Duration>) -> Result<(), PortError> {
+        let deadline = timeout.map(deadline_after);
+        self.ring.push_wait(N, deadline.as_ref(), |index| unsafe {
+            self.slot(index).write(item)
+        })
+    }
+
+    /// Like [`QueuingPort::dequeue`], but waits for a message for up to
+    /// `timeout`, or indefinitely for `None`.
+    pub fn dequeue_timeout(&self, timeout: Option<Duration>) -> Result<T, PortError> {
+        self.dequeue_with_timeout(timeout, |item| Ok(*item))
+    }
+
+    /// Whether a message was turned away because the port was full since the
+    /// overflow was last reported.
+    pub fn overflowed(&self) -> bool {
+        self.ring.overflowed()
+    }
+
+    /// Reports and clears the overflow flag.
+    pub fn take_overflow(&self) -> bool {
+        self.ring.take_overflow()
+    }
+
+    pub(crate) fn recover(&self, reset: bool) {
+        self.ring.recover(reset);
+    }
+
+    /// Blocked callers on the `direction` end of this port are released in
+    /// `discipline` order.
+    pub fn set_discipline(&self, direction: PortDirection, discipline: QueuingDiscipline) {
+        self.ring.waiters(direction).set_discipline(discipline);
+    }
+
+    pub fn discipline(&self, direction: PortDirection) -> QueuingDiscipline {
+        self.ring.waiters(direction).discipline()
+    }
+
+    /// Number of callers currently blocked on the `direction` end.
+    pub fn waiting(&self, direction: PortDirection) -> usize {
+        self.ring.waiters(direction).len()
+    }
+
+    fn dequeue_with<R>(&self, f: impl FnOnce(&T) -> Result<R, PortError>) -> Result<R, PortError> {
+        self.ring.pop(N, |index| f(unsafe { &*self.slot(index) }))
+    }
+
+    fn dequeue_with_timeout<R>(
+        &self,
+        timeout: Option<Duration>,
+        f: impl FnOnce(&T) -> Result<R, PortError>,
+    ) -> Result<R, PortError> {
+        let deadline = timeout.map(deadline_after);
+        self.ring.pop_wait(N, deadline.as_ref(), |index| {
+            f(unsafe { &*self.slot(index) })
+        })
+    }
+}
+
+// === Variable-Length Messages ===
+
+/// A slot holding up to `N` payload bytes plus the length actually used.
+#[derive(Clone, Copy)]
+#[repr(C)]
+pub struct Message<const N: usize> {
+    len: usize,
+    data: [u8; N],
+}
+
+// Messages of different capacities can share a size once padded, so the
+// capacity goes into the tag.
+unsafe impl<const N: usize> Pod for Message<N> {
+    const TYPE_TAG: u64 = message_tag(N);
+}
+
+// The tag of `Message<max_message_size>`, for ports that only learn the size
+// at run time.
+pub(crate) const fn message_tag(max_message_size: usize) -> u64 {
+    fnv1a(
+        type_tag("Message"),
+        &(max_message_size as u64).to_le_bytes(),
+    )
+}
+
+impl<const N: usize> Message<N> {
+    pub const MAX_MESSAGE_SIZE: usize = N;
+
+    const SIZE_OK: () = assert!(N > 0, "a message needs a maximum size of at least 1 byte");
+
+    pub fn new(payload: &[u8]) -> Result<Self, PortError> {
+        let () = Self::SIZE_OK;
+
+        if payload.len() > N {
+            return Err(PortError::Oversize);
+        }
+
+        let mut data = [0; N];
+        data[..payload.len()].copy_from_slice(payload);
+        Ok(Self {
+            len: payload.len(),
+            data,
+        })
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.data[..self.len.min(N)]
+    }
+}
+
+/// The outcome of a successful receive.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Received {
+    /// Number of bytes copied into the caller's buffer.
+    pub len: usize,
+    /// Whether a message was turned away because the port was full since the
+    /// previous receive. Reporting it clears the port's overflow flag.
+    pub overflow: bool,
+}
+
+/// A queuing port of `N` slots carrying byte messages of at most `M` bytes each.
+pub type MessagePort<const M: usize, const N: usize = MSG_COUNT> = QueuingPort<Message<M>, N>;
+
+impl<const M: usize, const N: usize> QueuingPort<Message<M>, N> {
+    pub fn send(&self, payload: &[u8]) -> Result<(), PortError> {
+        self.enqueue(Message::new(payload)?)
+    }
+
+    pub fn receive(&self, buf: &mut [u8]) -> Result<Received, PortError> {
+        let len = self.dequeue_with(|message| Self::copy_out(message, buf))?;
+        Ok(self.ring.received(len))
+    }
+
+    pub fn send_timeout(&self, payload: &[u8], timeout: Option<Duration>) -> Result<(), PortError> {
+        self.enqueue_timeout(Message::new(payload)?, timeout)
+    }
+
+    pub fn receive_timeout(
+        &self,
+        buf: &mut [u8],
+        timeout: Option<Duration>,
+    ) -> Result<Received, PortError> {
+        let len = self.dequeue_with_timeout(timeout, |message| Self::copy_out(message, buf))?;
+        Ok(self.ring.received(len))
+    }
+
+    fn copy_out(message: &Message<M>, buf: &mut [u8]) -> Result<usize, PortError> {
+        let payload = message.as_bytes();
+        if payload.len() > buf.len() {
+            return Err(PortError::Oversize);
+        }
+
+        buf[..payload.len()].copy_from_slice(payload);
+        Ok(payload.len())
+    }
+}
+
+// === Tests ===
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Clone, Copy, Debug, PartialEq)]
+    #[repr(C)]
+    struct Telemetry {
+        seq: u16,
+        altitude: f64,
+    }
+
+    unsafe impl Pod for Telemetry {
+        const TYPE_TAG: u64 = type_tag("Telemetry");
+    }
+
+    #[test]
+    fn test_struct_messages() {
+        let port = QueuingPort::<Telemetry>::new();
+
+        port.enqueue(Telemetry {
+            seq: 1,
+            altitude: 1000.5,
+        })
+        .unwrap();
+        port.enqueue(Telemetry {
+            seq: 2,
+            altitude: 1001.25,
+        })
+        .unwrap();
+
+        assert_eq!(
+            port.dequeue().unwrap(),
+            Telemetry {
+                seq: 1,
+                altitude: 1000.5
+            }
+        );
+        assert_eq!(
+            port.dequeue().unwrap(),
+            Telemetry {
+                seq: 2,
+                altitude: 1001.25
+            }
+        );
+        assert!(port.dequeue().is_err());
+    }
+
+    #[test]
+    fn test_variable_length_messages() {
+        let port = MessagePort::<8>::new();
+
+        port.send(b"hi").unwrap();
+        port.send(b"").unwrap();
+        port.send(b"12345678").unwrap();
+        assert_eq!(port.send(b"123456789"), Err(PortError::Oversize));
+
+        let mut buf = [0u8; 8];
+        assert_eq!(port.receive(&mut buf).map(|r| r.len), Ok(2));
+        assert_eq!(&buf[..2], b"hi");
+        assert_eq!(port.receive(&mut buf).map(|r| r.len), Ok(0));
+
+        let mut small = [0u8; 4];
+        assert_eq!(port.receive(&mut small), Err(PortError::Oversize));
+        assert_eq!(port.receive(&mut buf).map(|r| r.len), Ok(8));
+        assert_eq!(&buf, b"12345678");
+        assert_eq!(port.receive(&mut buf), Err(PortError::Empty));
+    }
+
+    #[test]
+    fn test_overflow_reported_once() {
+        let port = MessagePort::<4, 2>::new();
+        let mut buf = [0u8; 4];
+
+        port.send(b"a").unwrap();
+        port.send(b"b").unwrap();
+        assert!(!port.overflowed());
+        assert_eq!(port.send(b"c"), Err(PortError::Full));
+        assert!(port.overflowed());
+
+        assert_eq!(
+            port.receive(&mut buf),
+            Ok(Received {
+                len: 1,
+                overflow: true
+            })
+        );
+        assert!(!port.overflowed());
+        assert_eq!(port.receive(&mut buf).map(|r| r.overflow), Ok(false));
+
+        port.send(b"d").unwrap();
+        port.send(b"e").unwrap();
+        assert_eq!(
+            port.send_timeout(b"f", Some(Duration::from_millis(5))),
+            Err(PortError::Timeout)
+        );
+        assert!(port.take_overflow());
+        assert!(!port.take_overflow());
+    }
+
+    #[test]
+    fn test_per_port_capacity() {
+        let command = QueuingPort::<u8, 4>::new();
+        let telemetry = MessagePort::<32, 512>::new();
+
+        assert_eq!(command.capacity(), 4);
+        assert_eq!(telemetry.capacity(), 512);
+
+        for i in 0..4 {
+            command.enqueue(i).unwrap();
+        }
+        assert_eq!(command.enqueue(4), Err(PortError::Full));
+        assert_eq!(command.len(), 4);
+
+        command.clear();
+        assert!(command.is_empty());
+        assert_eq!(command.dequeue(), Err(PortError::Empty));
+
+        for _ in 0..512 {
+            telemetry.send(b"sample").unwrap();
+        }
+        assert_eq!(telemetry.send(b"sample"), Err(PortError::Full));
+    }
+
+    #[test]
+    fn test_full_capacity_wraparound() {
+        let port = QueuingPort::<u32, 3>::new();
+        let mut next_in = 0;
+        let mut next_out = 0;
+
+        // Each lap tops the port up and takes one message off, moving the
+        // counters one step, so over more laps than twice the slot count the
+        // port fills and empties at every counter position.
+        for _ in 0..8 {
+            while port.enqueue(next_in).is_ok() {
+                next_in += 1;
+            }
+            assert_eq!(port.len(), 3);
+            assert_eq!(port.dequeue(), Ok(next_out));
+            next_out += 1;
+        }
+        for _ in 0..8 {
+            while let Ok(value) = port.dequeue() {
+                assert_eq!(value, next_out);
+                next_out += 1;
+            }
+            assert_eq!(next_out, next_in);
+            port.enqueue(next_in).unwrap();
+            next_in += 1;
+        }
+
+        let single = MessagePort::<4, 1>::new();
+        let mut buf = [0u8; 4];
+        for message in [b"a", b"b", b"c"] {
+            single.send(message).unwrap();
+            assert_eq!(single.send(b"x"), Err(PortError::Full));
+            assert_eq!(single.receive(&mut buf).map(|r| r.len), Ok(1));
+            assert_eq!(&buf[..1], message);
+        }
+    }
+}
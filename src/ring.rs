@@ -0,0 +1,208 @@
+use core::sync::atomic::{AtomicU32, AtomicUsize, Ordering};
+
+use crate::direction::PortDirection;
+use crate::error::PortError;
+use crate::futex::{Deadline, Event};
+use crate::port::Received;
+use crate::wait::{current_priority, WaitQueue};
+
+// Read and write counters over `slots` slots. The slot storage itself lives
+// with the port that owns the ring. The counters run over twice the slot
+// count, so a full ring (`slots` apart) differs from an empty one (equal) and
+// every slot can hold a message. Writers take turns through `senders` and
+// readers through `receivers`, so each counter has one writer at a time and
+// any number of callers may share either end. Blocked readers queue in
+// `receivers` and sleep on `pushed`; blocked writers queue in `senders` and
+// sleep on `popped`. `overflow` is raised whenever a writer is turned away
+// and stays up until the reader has been told about it.
+#[repr(C)]
+pub(crate) struct Ring {
+    write_count: AtomicUsize,
+    read_count: AtomicUsize,
+    pushed: Event,
+    popped: Event,
+    senders: WaitQueue,
+    receivers: WaitQueue,
+    overflow: AtomicU32,
+}
+
+// The counter after `count`, wrapping at twice the slot count.
+fn advance(count: usize, slots: usize) -> usize {
+    (count + 1) % (2 * slots)
+}
+
+impl Ring {
+    pub(crate) const fn new() -> Self {
+        Self {
+            write_count: AtomicUsize::new(0),
+            read_count: AtomicUsize::new(0),
+            pushed: Event::new(),
+            popped: Event::new(),
+            senders: WaitQueue::new(),
+            receivers: WaitQueue::new(),
+            overflow: AtomicU32::new(0),
+        }
+    }
+
+    pub(crate) fn waiters(&self, direction: PortDirection) -> &WaitQueue {
+        match direction {
+            PortDirection::Source => &self.senders,
+            PortDirection::Destination => &self.receivers,
+        }
+    }
+
+    pub(crate) fn overflowed(&self) -> bool {
+        self.overflow.load(Ordering::Acquire) != 0
+    }
+
+    // Clears the overflow flag, returning whether it was set.
+    pub(crate) fn take_overflow(&self) -> bool {
+        self.overflow.swap(0, Ordering::AcqRel) != 0
+    }
+
+    // Reports a message of `len` bytes to the reader along with any overflow
+    // since the previous report.
+    pub(crate) fn received(&self, len: usize) -> Received {
+        Received {
+            len,
+            overflow: self.take_overflow(),
+        }
+    }
+
+    fn is_full(&self, slots: usize) -> bool {
+        self.len(slots) == slots
+    }
+
+    fn is_empty(&self) -> bool {
+        self.read_count.load(Ordering::Acquire) == self.write_count.load(Ordering::Acquire)
+    }
+
+    // Like `push`, but first waits in line with other blocked writers until a
+    // slot is free or `deadline` passes.
+    pub(crate) fn push_wait(
+        &self,
+        slots: usize,
+        deadline: Option<&Deadline>,
+        write: impl FnOnce(usize),
+    ) -> Result<(), PortError> {
+        let result = self.senders.run(
+            &self.popped,
+            current_priority(),
+            deadline,
+            || self.is_full(slots),
+            || self.push_turn(slots, write),
+        );
+        if result == Err(PortError::Timeout) {
+            self.overflow.store(1, Ordering::Release);
+        }
+        result?
+    }
+
+    // Like `pop`, but first waits in line with other blocked readers until a
+    // message arrives or `deadline` passes.
+    pub(crate) fn pop_wait<R>(
+        &self,
+        slots: usize,
+        deadline: Option<&Deadline>,
+        read: impl FnOnce(usize) -> Result<R, PortError>,
+    ) -> Result<R, PortError> {
+        self.receivers.run(
+            &self.pushed,
+            current_priority(),
+            deadline,
+            || self.is_empty(),
+            || self.pop_turn(slots, read),
+        )?
+    }
+
+    pub(crate) fn len(&self, slots: usize) -> usize {
+        let write_count = self.write_count.load(Ordering::Acquire);
+        let read_count = self.read_count.load(Ordering::Acquire);
+        (write_count + 2 * slots - read_count) % (2 * slots)
+    }
+
+    // Drops every queued message, in turn with the other readers.
+    pub(crate) fn clear(&self) {
+        self.receivers.exclusive(|| {
+            let write_count = self.write_count.load(Ordering::Acquire);
+            self.read_count.store(write_count, Ordering::Release);
+        });
+        self.pushed.notify();
+        self.popped.notify();
+    }
+
+    // Makes the ring usable again after a process using it died: frees the
+    // waiter entries and the turn it held and, for `reset`, also drops every
+    // queued message and any pending overflow. The surviving end may keep
+    // using the ring meanwhile; a message it is popping may just survive the
+    // reset.
+    pub(crate) fn recover(&self, reset: bool) {
+        self.senders.purge_dead();
+        self.receivers.purge_dead();
+        if reset {
+            let write_count = self.write_count.load(Ordering::Acquire);
+            self.read_count.store(write_count, Ordering::Release);
+            self.overflow.store(0, Ordering::Release);
+        }
+        self.pushed.notify();
+        self.popped.notify();
+    }
+
+    // Hands the next free slot to `write` and publishes it to the reader,
+    // taking the writers' turn so concurrent writers never share a slot.
+    pub(crate) fn push(&self, slots: usize, write: impl FnOnce(usize)) -> Result<(), PortError> {
+        let result = self.senders.exclusive(|| self.push_turn(slots, write));
+        self.popped.notify();
+        result
+    }
+
+    // Like `pop`, but without waiting, in turn with the other readers.
+    pub(crate) fn pop<R>(
+        &self,
+        slots: usize,
+        read: impl FnOnce(usize) -> Result<R, PortError>,
+    ) -> Result<R, PortError> {
+        let result = self.receivers.exclusive(|| self.pop_turn(slots, read));
+        self.pushed.notify();
+        result
+    }
+
+    // `push` for the caller holding the writers' turn.
+    fn push_turn(&self, slots: usize, write: impl FnOnce(usize)) -> Result<(), PortError> {
+        let write_count = self.write_count.load(Ordering::Relaxed);
+
+        if self.is_full(slots) {
+            self.overflow.store(1, Ordering::Release);
+            return Err(PortError::Full);
+        }
+
+        write(write_count % slots);
+
+        self.write_count
+            .store(advance(write_count, slots), Ordering::Release);
+        self.pushed.notify();
+        Ok(())
+    }
+
+    // For the caller holding the readers' turn: hands the oldest slot to
+    // `read` and only releases it if `read` succeeds, so a rejected read
+    // leaves the message in the queue.
+    fn pop_turn<R>(
+        &self,
+        slots: usize,
+        read: impl FnOnce(usize) -> Result<R, PortError>,
+    ) -> Result<R, PortError> {
+        let read_count = self.read_count.load(Ordering::Relaxed);
+
+        if read_count == self.write_count.load(Ordering::Acquire) {
+            return Err(PortError::Empty);
+        }
+
+        let value = read(read_count % slots)?;
+
+        self.read_count
+            .store(advance(read_count, slots), Ordering::Release);
+        self.popped.notify();
+        Ok(value)
+    }
+}
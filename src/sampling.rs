@@ -0,0 +1,196 @@
+use core::hint::spin_loop;
+use core::sync::atomic::{fence, AtomicU64, AtomicU8, AtomicUsize, Ordering};
+use core::time::Duration;
+
+use crate::error::PortError;
+use crate::futex::monotonic_now;
+
+/// The outcome of reading a sampling port.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Sample {
+    /// Number of bytes copied into the caller's buffer.
+    pub len: usize,
+    /// Time since the message was written.
+    pub age: Duration,
+    /// Whether the message is no older than the port's refresh period.
+    pub valid: bool,
+}
+
+/// A sampling port holding the latest message of at most `M` bytes.
+///
+/// Every write overwrites the previous message and every read leaves it in
+/// place. The message is published under a seqlock: the writer keeps `seq`
+/// odd while it updates the message, and readers retry whenever they saw an
+/// odd or changed `seq`, so a reader in another process never sees a torn
+/// message. All-zero memory is a valid port that has never been written.
+#[repr(C)]
+pub struct SamplingPort<const M: usize> {
+    seq: AtomicU64,
+    refresh_period: AtomicU64,
+    written_at: AtomicU64,
+    len: AtomicUsize,
+    data: [AtomicU8; M],
+}
+
+impl<const M: usize> SamplingPort<M> {
+    pub const MAX_MESSAGE_SIZE: usize = M;
+
+    const SIZE_OK: () = assert!(M > 0, "a message needs a maximum size of at least 1 byte");
+
+    pub fn new(refresh_period: Duration) -> Self {
+        let () = Self::SIZE_OK;
+
+        let port = Self {
+            seq: AtomicU64::new(0),
+            refresh_period: AtomicU64::new(0),
+            written_at: AtomicU64::new(0),
+            len: AtomicUsize::new(0),
+            data: [const { AtomicU8::new(0) }; M],
+        };
+        port.set_refresh_period(refresh_period);
+        port
+    }
+
+    pub fn max_message_size(&self) -> usize {
+        M
+    }
+
+    pub fn refresh_period(&self) -> Duration {
+        Duration::from_nanos(self.refresh_period.load(Ordering::Relaxed))
+    }
+
+    pub(crate) fn set_refresh_period(&self, refresh_period: Duration) {
+        let nanos = refresh_period.as_nanos().min(u64::MAX as u128) as u64;
+        self.refresh_period.store(nanos, Ordering::Relaxed);
+    }
+
+    /// Whether no message has been written yet.
+    pub fn is_empty(&self) -> bool {
+        self.seq.load(Ordering::Acquire) == 0
+    }
+
+    // Makes the port usable again after a process using it died. A writer
+    // that died mid-write leaves `seq` odd, which would stall every reader,
+    // so that message is dropped just as `reset` drops any message.
+    pub(crate) fn recover(&self, reset: bool) {
+        let seq = self.seq.load(Ordering::Acquire);
+        if reset || seq % 2 == 1 {
+            let _ = self
+                .seq
+                .compare_exchange(seq, 0, Ordering::AcqRel, Ordering::Relaxed);
+        }
+    }
+
+    /// WRITE_SAMPLING_MESSAGE. Replaces the current message with `payload`.
+    pub fn write_sampling_message(&self, payload: &[u8]) -> Result<(), PortError> {
+        if payload.len() > M {
+            return Err(PortError::Oversize);
+        }
+
+        // Claim the port by making `seq` odd; a second writer waits its turn.
+        let mut seq = self.seq.load(Ordering::Relaxed);
+        loop {
+            if seq % 2 == 1 {
+                spin_loop();
+                seq = self.seq.load(Ordering::Relaxed);
+                continue;
+            }
+            match self
+                .seq
+                .compare_exchange_weak(seq, seq + 1, Ordering::Acquire, Ordering::Relaxed)
+            {
+                Ok(_) => break,
+                Err(current) => seq = current,
+            }
+        }
+        fence(Ordering::Release);
+
+        for (byte, &value) in self.data.iter().zip(payload) {
+            byte.store(value, Ordering::Relaxed);
+        }
+        self.len.store(payload.len(), Ordering::Relaxed);
+        self.written_at
+            .store(monotonic_now().as_nanos() as u64, Ordering::Relaxed);
+
+        self.seq.store(seq + 2, Ordering::Release);
+        Ok(())
+    }
+
+    /// READ_SAMPLING_MESSAGE. Copies the current message into `buf` and
+    /// reports its age and validity.
+    pub fn read_sampling_message(&self, buf: &mut [u8]) -> Result<Sample, PortError> {
+        loop {
+            let seq = self.seq.load(Ordering::Acquire);
+            if seq == 0 {
+                return Err(PortError::Empty);
+            }
+            if seq % 2 == 1 {
+                spin_loop();
+                continue;
+            }
+
+            let len = self.len.load(Ordering::Relaxed).min(M);
+            let written_at = self.written_at.load(Ordering::Relaxed);
+            if len <= buf.len() {
+                for (value, byte) in buf.iter_mut().zip(&self.data[..len]) {
+                    *value = byte.load(Ordering::Relaxed);
+                }
+            }
+
+            fence(Ordering::Acquire);
+            if self.seq.load(Ordering::Relaxed) != seq {
+                continue;
+            }
+
+            if len > buf.len() {
+                return Err(PortError::Oversize);
+            }
+
+            let age = monotonic_now().saturating_sub(Duration::from_nanos(written_at));
+            return Ok(Sample {
+                len,
+                age,
+                valid: age <= self.refresh_period(),
+            });
+        }
+    }
+}
+
+// === Tests ===
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sampling_overwrite_and_validity() {
+        let port = SamplingPort::<8>::new(Duration::from_millis(20));
+        let mut buf = [0u8; 8];
+
+        assert_eq!(port.read_sampling_message(&mut buf), Err(PortError::Empty));
+        assert_eq!(
+            port.write_sampling_message(b"123456789"),
+            Err(PortError::Oversize)
+        );
+
+        port.write_sampling_message(b"first").unwrap();
+        port.write_sampling_message(b"second").unwrap();
+
+        let sample = port.read_sampling_message(&mut buf).unwrap();
+        assert_eq!(&buf[..sample.len], b"second");
+        assert!(sample.valid);
+
+        // Reading leaves the message in place, but it goes stale.
+        std::thread::sleep(Duration::from_millis(30));
+        let sample = port.read_sampling_message(&mut buf).unwrap();
+        assert_eq!(&buf[..sample.len], b"second");
+        assert!(sample.age >= Duration::from_millis(30));
+        assert!(!sample.valid);
+
+        let mut small = [0u8; 4];
+        assert_eq!(
+            port.read_sampling_message(&mut small),
+            Err(PortError::Oversize)
+        );
+    }
+}
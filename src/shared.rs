@@ -0,0 +1,443 @@
+use alloc::collections::BTreeMap;
+use alloc::ffi::CString;
+use alloc::string::{String, ToString};
+use alloc::sync::Arc;
+use core::any::Any;
+use core::mem::{align_of, size_of};
+use core::ptr::NonNull;
+
+use shared_memory::{Shmem, ShmemConf, ShmemError};
+use spin::Mutex;
+
+use crate::error::PortError;
+use crate::handle::PortHandle;
+use crate::header::{RecoveryPolicy, SegmentHeader, SegmentLayout, HEADER_SIZE};
+use crate::port::{Message, Pod, QueuingPort, Received};
+
+// === Shared Memory Setup ===
+
+#[derive(Clone, Copy)]
+pub(crate) enum OpenMode {
+    Create,
+    Open,
+    OpenOrCreate,
+}
+
+// How a port's segment is opened: whether it may or must be created, and
+// what to do about processes that died using it.
+#[derive(Clone, Copy)]
+pub(crate) struct OpenOptions {
+    pub mode: OpenMode,
+    pub policy: RecoveryPolicy,
+}
+
+// Maps the segment named `os_id`, creating it with `size` bytes if `mode`
+// allows. Returns whether this call created it so the caller knows if the
+// contents still need initializing.
+pub(crate) fn map_segment(
+    os_id: &str,
+    size: usize,
+    mode: OpenMode,
+) -> Result<(Shmem, bool), PortError> {
+    let create = || ShmemConf::new().size(size).os_id(os_id).create();
+    let open = || ShmemConf::new().os_id(os_id).open();
+
+    match mode {
+        OpenMode::Create => match create() {
+            Ok(shmem) => Ok((shmem, true)),
+            Err(ShmemError::MappingIdExists) => Err(PortError::AlreadyExists),
+            Err(_) => Err(PortError::ShmCreate),
+        },
+        OpenMode::Open => match open() {
+            Ok(shmem) => Ok((shmem, false)),
+            Err(_) => Err(PortError::ShmOpen),
+        },
+        OpenMode::OpenOrCreate => match create() {
+            Ok(shmem) => Ok((shmem, true)),
+            Err(ShmemError::MappingIdExists) => map_segment(os_id, size, OpenMode::Open),
+            Err(_) => Err(PortError::ShmCreate),
+        },
+    }
+}
+
+/// Whether dropping a handle removes its segment from the system.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Lifetime {
+    /// The handle owns the segment and removes it when dropped. Processes
+    /// that still have it mapped keep using it, but nobody can open it.
+    Owned,
+    /// The segment outlives the handle until someone [`unlink`]s it.
+    Persistent,
+}
+
+/// Removes the segment `os_id` from the system, so the next `create` starts
+/// from a fresh port. Processes that have it mapped keep using the old one.
+/// Also forgets any mapping the `*_shared` functions hold for it.
+pub fn unlink(os_id: &str) -> Result<(), PortError> {
+    // Dropping a mapping this process created already removes the segment.
+    let held = REGISTRY.lock().remove(os_id).is_some();
+    let name = CString::new(os_id).map_err(|_| PortError::ShmOpen)?;
+    if unsafe { libc::shm_unlink(name.as_ptr()) } == 0 || held {
+        Ok(())
+    } else {
+        Err(PortError::ShmOpen)
+    }
+}
+
+/// A handle's mapping of its shared-memory segment: the header in front, the
+/// port behind it, and this process's record in the header, removed again
+/// when the handle is dropped.
+pub struct Segment {
+    shmem: Shmem,
+    header: NonNull<SegmentHeader>,
+    // `None` when this process is the creator.
+    entry: Option<usize>,
+}
+
+unsafe impl Send for Segment {}
+unsafe impl Sync for Segment {}
+
+impl Segment {
+    /// Number of times the segment was recovered from a dead process.
+    pub fn generation(&self) -> u32 {
+        unsafe { self.header.as_ref() }.generation()
+    }
+
+    /// Whether dropping the handle removes the segment: [`Lifetime::Owned`]
+    /// for the creating handle and [`Lifetime::Persistent`] for others.
+    pub fn lifetime(&self) -> Lifetime {
+        if self.shmem.is_owner() {
+            Lifetime::Owned
+        } else {
+            Lifetime::Persistent
+        }
+    }
+
+    pub fn set_lifetime(&mut self, lifetime: Lifetime) {
+        self.shmem.set_owner(lifetime == Lifetime::Owned);
+    }
+}
+
+impl Drop for Segment {
+    fn drop(&mut self) {
+        unsafe { self.header.as_ref() }.detach(self.entry);
+    }
+}
+
+// Maps `os_id` as a header followed by `size` bytes of port. The creator
+// builds the port with `init`, which also returns the layout to stamp, and
+// an attacher checks the stamped layout with `attach`.
+//
+// If a process recorded in the header has died, the recovery policy decides:
+// `recover` releases what it held in the port, and for `Reset` also drops
+// the queued messages. `Create` on a segment whose creator died takes the
+// segment over rather than failing.
+pub(crate) fn map_port_segment<P>(
+    os_id: &str,
+    size: usize,
+    options: OpenOptions,
+    type_hash: u64,
+    init: impl FnOnce(*mut u8, usize) -> Result<(P, SegmentLayout), PortError>,
+    attach: impl FnOnce(*mut u8, usize, &SegmentLayout) -> Result<P, PortError>,
+    recover: impl FnOnce(&P, bool),
+) -> Result<(Segment, P), PortError> {
+    let size = size
+        .checked_add(HEADER_SIZE)
+        .ok_or(PortError::LayoutMismatch)?;
+    let (mut shmem, created, takeover) = match map_segment(os_id, size, options.mode) {
+        Ok((shmem, created)) => (shmem, created, false),
+        Err(PortError::AlreadyExists) => (map_segment(os_id, size, OpenMode::Open)?.0, false, true),
+        Err(error) => return Err(error),
+    };
+
+    let len = shmem
+        .len()
+        .checked_sub(HEADER_SIZE)
+        .ok_or(PortError::LayoutMismatch)?;
+    let header = NonNull::new(shmem.as_ptr() as *mut SegmentHeader).ok_or(PortError::ShmOpen)?;
+    let memory = unsafe { shmem.as_ptr().add(HEADER_SIZE) };
+
+    if created {
+        let (port, layout) = init(memory, len)?;
+        unsafe { SegmentHeader::stamp(header.as_ptr(), &layout) };
+        let segment = Segment {
+            shmem,
+            header,
+            entry: None,
+        };
+        return Ok((segment, port));
+    }
+
+    let stamped = unsafe { header.as_ref() };
+    let layout = stamped.read(type_hash)?;
+    let port = attach(memory, len, &layout)?;
+    if takeover && !stamped.creator_died() {
+        return Err(PortError::AlreadyExists);
+    }
+
+    if stamped.has_dead() {
+        match options.policy {
+            RecoveryPolicy::Refuse if takeover => return Err(PortError::AlreadyExists),
+            RecoveryPolicy::Refuse => return Err(PortError::OwnerDied),
+            _ => {
+                recover(&port, options.policy == RecoveryPolicy::Reset);
+                stamped.purge_dead();
+            }
+        }
+    }
+
+    let entry = if takeover {
+        if !stamped.adopt() {
+            return Err(PortError::AlreadyExists);
+        }
+        shmem.set_owner(true);
+        None
+    } else {
+        Some(stamped.attach()?)
+    };
+    let segment = Segment {
+        shmem,
+        header,
+        entry,
+    };
+    Ok((segment, port))
+}
+
+// Maps `os_id` as a single `P` through `map_port_segment`. Every port type
+// kept in a segment of its own treats zero-filled memory as a valid empty
+// port, so the creator only runs `init` for settings that are not zero.
+pub(crate) fn map_port<P>(
+    os_id: &str,
+    options: OpenOptions,
+    layout: &SegmentLayout,
+    init: impl FnOnce(&P),
+    recover: impl FnOnce(&P, bool),
+) -> Result<(Segment, NonNull<P>), PortError> {
+    if align_of::<P>() > HEADER_SIZE {
+        return Err(PortError::LayoutMismatch);
+    }
+    let port = |memory: *mut u8, len: usize| {
+        if len < size_of::<P>() {
+            return Err(PortError::LayoutMismatch);
+        }
+        NonNull::new(memory as *mut P).ok_or(PortError::ShmOpen)
+    };
+
+    map_port_segment(
+        os_id,
+        size_of::<P>(),
+        options,
+        layout.type_hash,
+        |memory, len| {
+            let port = port(memory, len)?;
+            init(unsafe { port.as_ref() });
+            Ok((port, *layout))
+        },
+        |memory, len, stamped| {
+            if stamped != layout {
+                return Err(PortError::LayoutMismatch);
+            }
+            port(memory, len)
+        },
+        |port, reset| recover(unsafe { port.as_ref() }, reset),
+    )
+}
+
+// === Port Registry ===
+
+// Every port opened through the `*_shared` functions, keyed by os_id. Handles
+// stay registered until released so repeated calls reuse the same mapping.
+static REGISTRY: Mutex<BTreeMap<String, Arc<dyn Any + Send + Sync>>> = Mutex::new(BTreeMap::new());
+
+fn get_shared_queue<T: Pod, const N: usize>(
+    os_id: &str,
+) -> Result<Arc<PortHandle<T, N>>, PortError> {
+    // Opening may wait for another process to stamp the segment, so it runs
+    // without the lock held.
+    let registered = REGISTRY.lock().get(os_id).cloned();
+    let handle = match registered {
+        Some(handle) => handle,
+        None => {
+            let opened = Arc::new(PortHandle::<T, N>::open_or_create(os_id)?);
+            let mut registry = REGISTRY.lock();
+            match registry.get(os_id) {
+                // Another thread registered the segment meanwhile. A handle
+                // that created it must stay registered instead, since dropping
+                // it removes the segment.
+                Some(handle) if opened.segment().lifetime() != Lifetime::Owned => handle.clone(),
+                _ => {
+                    registry.insert(os_id.to_string(), opened.clone());
+                    return Ok(opened);
+                }
+            }
+        }
+    };
+
+    handle
+        .downcast::<PortHandle<T, N>>()
+        .map_err(|_| PortError::LayoutMismatch)
+}
+
+// === Public API ===
+
+/// Drops the mapping the `*_shared` functions hold for `os_id`. If this
+/// process created the segment it is removed, as when its handle is dropped.
+/// Returns whether a mapping was held.
+pub fn release_shared(os_id: &str) -> bool {
+    let handle = REGISTRY.lock().remove(os_id);
+    handle.is_some()
+}
+
+impl<T: Pod, const N: usize> QueuingPort<T, N> {
+    pub fn enqueue_shared(item: T, os_id: &str) -> Result<(), PortError> {
+        get_shared_queue::<T, N>(os_id)?.enqueue(item)
+    }
+
+    pub fn dequeue_shared(os_id: &str) -> Result<T, PortError> {
+        get_shared_queue::<T, N>(os_id)?.dequeue()
+    }
+}
+
+impl<const M: usize, const N: usize> QueuingPort<Message<M>, N> {
+    pub fn send_shared(payload: &[u8], os_id: &str) -> Result<(), PortError> {
+        get_shared_queue::<Message<M>, N>(os_id)?.send(payload)
+    }
+
+    pub fn receive_shared(buf: &mut [u8], os_id: &str) -> Result<Received, PortError> {
+        get_shared_queue::<Message<M>, N>(os_id)?.receive(buf)
+    }
+}
+
+// === Tests ===
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::thread;
+
+    #[test]
+    fn test_basic_enqueue_dequeue_shared() {
+        let os_id = "test_queue_1";
+        let _ = unlink(os_id);
+
+        QueuingPort::<i32>::enqueue_shared(10, os_id).unwrap();
+        QueuingPort::<i32>::enqueue_shared(20, os_id).unwrap();
+
+        let x = QueuingPort::<i32>::dequeue_shared(os_id).unwrap();
+        let y = QueuingPort::<i32>::dequeue_shared(os_id).unwrap();
+
+        println!("Dequeued values: {}, {}", x, y);
+        assert_eq!(x, 10);
+        assert_eq!(y, 20);
+        assert!(release_shared(os_id));
+    }
+
+    #[test]
+    fn test_single_writer_reader() {
+        let os_id = "test_queue_fixed";
+        let _ = unlink(os_id);
+
+        //writer thread
+        let writer = thread::spawn({
+            let id = os_id.to_string();
+            move || {
+                for i in 0..10 {
+                    let _ = QueuingPort::<i32>::enqueue_shared(i, &id);
+                }
+            }
+        });
+
+        writer.join().unwrap();
+
+        // reader thread
+        let reader = thread::spawn({
+            let id = os_id.to_string();
+            move || {
+                let mut results = vec![];
+                for _ in 0..10 {
+                    if let Ok(val) = QueuingPort::<i32>::dequeue_shared(&id) {
+                        results.push(val);
+                    }
+                }
+
+                results.sort();
+                assert_eq!(results, (0..10).collect::<Vec<_>>());
+            }
+        });
+
+        reader.join().unwrap();
+        release_shared(os_id);
+    }
+
+    #[test]
+    fn test_registry_keeps_ports_apart() {
+        let _ = unlink("test_registry_a");
+        let _ = unlink("test_registry_b");
+        QueuingPort::<i32>::enqueue_shared(1, "test_registry_a").unwrap();
+        QueuingPort::<i32>::enqueue_shared(2, "test_registry_b").unwrap();
+
+        assert_eq!(QueuingPort::<i32>::dequeue_shared("test_registry_b"), Ok(2));
+        assert_eq!(QueuingPort::<i32>::dequeue_shared("test_registry_a"), Ok(1));
+        assert_eq!(
+            QueuingPort::<i32>::dequeue_shared("test_registry_a"),
+            Err(PortError::Empty)
+        );
+        assert_eq!(
+            QueuingPort::<u64>::dequeue_shared("test_registry_a"),
+            Err(PortError::LayoutMismatch)
+        );
+        release_shared("test_registry_a");
+        release_shared("test_registry_b");
+    }
+
+    #[test]
+    fn test_registry_from_many_threads() {
+        let workers: Vec<_> = (0..8)
+            .map(|i| {
+                thread::spawn(move || {
+                    let os_id = format!("test_registry_thread_{}", i);
+                    let _ = unlink(&os_id);
+                    for value in 0..5 {
+                        QueuingPort::<i32>::enqueue_shared(value, &os_id).unwrap();
+                    }
+                    let values = (0..5)
+                        .map(|_| QueuingPort::<i32>::dequeue_shared(&os_id).unwrap())
+                        .collect::<Vec<_>>();
+                    release_shared(&os_id);
+                    values
+                })
+            })
+            .collect();
+
+        for worker in workers {
+            assert_eq!(worker.join().unwrap(), vec![0, 1, 2, 3, 4]);
+        }
+    }
+
+    #[test]
+    fn test_registry_race_keeps_creator() {
+        let os_id = "test_registry_race";
+        let _ = unlink(os_id);
+
+        // Threads first opening the same segment at once all end up on one
+        // port, and the mapping that created it stays registered.
+        let workers: Vec<_> = (0..8)
+            .map(|i| thread::spawn(move || QueuingPort::<i32>::enqueue_shared(i, os_id)))
+            .collect();
+        for worker in workers {
+            worker.join().unwrap().unwrap();
+        }
+        assert!(PortHandle::<i32>::open(os_id).is_ok());
+
+        let mut values: Vec<_> = (0..8)
+            .map(|_| QueuingPort::<i32>::dequeue_shared(os_id).unwrap())
+            .collect();
+        values.sort_unstable();
+        assert_eq!(values, (0..8).collect::<Vec<_>>());
+        assert!(release_shared(os_id));
+        assert_eq!(
+            PortHandle::<i32>::open(os_id).err(),
+            Some(PortError::ShmOpen)
+        );
+    }
+}
@@ -0,0 +1,263 @@
+use core::hint::spin_loop;
+use core::sync::atomic::{AtomicU32, AtomicU64, Ordering};
+
+use crate::error::PortError;
+use crate::futex::{current_pid, process_alive, Deadline, Event};
+
+/// Order in which processes blocked on the same end of a port are released.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(
+    feature = "std",
+    derive(serde::Deserialize),
+    serde(rename_all = "lowercase")
+)]
+#[repr(u32)]
+pub enum QueuingDiscipline {
+    #[default]
+    Fifo = 0,
+    Priority = 1,
+}
+
+pub type Priority = u8;
+
+#[cfg(feature = "std")]
+std::thread_local! {
+    static CURRENT_PRIORITY: core::cell::Cell<Priority> = const { core::cell::Cell::new(0) };
+}
+
+/// Sets the priority the calling thread waits with on ports using the
+/// PRIORITY discipline. Higher values are released first.
+#[cfg(feature = "std")]
+pub fn set_current_priority(priority: Priority) {
+    CURRENT_PRIORITY.with(|current| current.set(priority));
+}
+
+#[cfg(feature = "std")]
+pub fn current_priority() -> Priority {
+    CURRENT_PRIORITY.with(|current| current.get())
+}
+
+#[cfg(not(feature = "std"))]
+pub fn current_priority() -> Priority {
+    0
+}
+
+pub const MAX_WAITERS: usize = 32;
+
+// Spins between checks that the holder of the turn is still alive.
+const LIVENESS_SPINS: u32 = 1 << 12;
+
+const TICKET_BITS: u32 = 56;
+const TICKET_MASK: u64 = (1 << TICKET_BITS) - 1;
+
+// Processes blocked on one end of a port, kept in shared memory. Each entry
+// packs the waiter's priority above its arrival ticket; zero marks a free
+// entry, and `pids` records which process holds it. Every operation on the
+// end claims `active` with its PID for its duration: blocked callers once
+// the discipline puts them first, and callers that do not wait straight
+// away. So operations on one end never overlap, and blocked callers are
+// served in order. All-zero is a valid empty queue using the FIFO discipline.
+#[repr(C)]
+pub(crate) struct WaitQueue {
+    entries: [AtomicU64; MAX_WAITERS],
+    pids: [AtomicU32; MAX_WAITERS],
+    next_ticket: AtomicU64,
+    active: AtomicU32,
+    discipline: AtomicU32,
+}
+
+impl WaitQueue {
+    pub(crate) const fn new() -> Self {
+        Self {
+            entries: [const { AtomicU64::new(0) }; MAX_WAITERS],
+            pids: [const { AtomicU32::new(0) }; MAX_WAITERS],
+            next_ticket: AtomicU64::new(0),
+            active: AtomicU32::new(0),
+            discipline: AtomicU32::new(QueuingDiscipline::Fifo as u32),
+        }
+    }
+
+    pub(crate) fn discipline(&self) -> QueuingDiscipline {
+        match self.discipline.load(Ordering::Relaxed) {
+            1 => QueuingDiscipline::Priority,
+            _ => QueuingDiscipline::Fifo,
+        }
+    }
+
+    pub(crate) fn set_discipline(&self, discipline: QueuingDiscipline) {
+        self.discipline.store(discipline as u32, Ordering::Relaxed);
+    }
+
+    pub(crate) fn len(&self) -> usize {
+        self.entries
+            .iter()
+            .filter(|entry| entry.load(Ordering::Acquire) != 0)
+            .count()
+    }
+
+    fn register(&self, priority: Priority) -> Result<usize, PortError> {
+        let ticket = (self.next_ticket.fetch_add(1, Ordering::Relaxed) + 1) & TICKET_MASK;
+        let entry = (u64::from(priority) << TICKET_BITS) | ticket.max(1);
+
+        for (index, slot) in self.entries.iter().enumerate() {
+            if slot
+                .compare_exchange(0, entry, Ordering::AcqRel, Ordering::Relaxed)
+                .is_ok()
+            {
+                self.pids[index].store(current_pid(), Ordering::Release);
+                return Ok(index);
+            }
+        }
+        Err(PortError::TooManyWaiters)
+    }
+
+    fn unregister(&self, index: usize) {
+        self.pids[index].store(0, Ordering::Release);
+        self.entries[index].store(0, Ordering::Release);
+    }
+
+    // Frees the entries and the turn held by processes that died while
+    // blocked or while performing their operation. Returns whether any were
+    // found; the caller then notifies the event the waiters sleep on.
+    pub(crate) fn purge_dead(&self) -> bool {
+        let mut purged = false;
+        for (entry, pid) in self.entries.iter().zip(&self.pids) {
+            let owner = pid.load(Ordering::Acquire);
+            if owner != 0
+                && !process_alive(owner)
+                && pid
+                    .compare_exchange(owner, 0, Ordering::AcqRel, Ordering::Relaxed)
+                    .is_ok()
+            {
+                entry.store(0, Ordering::Release);
+                purged = true;
+            }
+        }
+
+        let active = self.active.load(Ordering::Acquire);
+        if active != 0 && !process_alive(active) {
+            purged |= self
+                .active
+                .compare_exchange(active, 0, Ordering::AcqRel, Ordering::Relaxed)
+                .is_ok();
+        }
+        purged
+    }
+
+    // Whether no other registered waiter goes before entry `index`.
+    fn is_first(&self, index: usize) -> bool {
+        let rank = |entry: u64| match self.discipline() {
+            QueuingDiscipline::Fifo => (0, entry & TICKET_MASK),
+            QueuingDiscipline::Priority => (
+                Priority::MAX - (entry >> TICKET_BITS) as Priority,
+                entry & TICKET_MASK,
+            ),
+        };
+
+        let own = rank(self.entries[index].load(Ordering::Acquire));
+        self.entries
+            .iter()
+            .map(|entry| entry.load(Ordering::Acquire))
+            .filter(|&entry| entry != 0)
+            .all(|entry| rank(entry) >= own)
+    }
+
+    // Queues the caller behind other blocked callers and sleeps on `event`
+    // until it is first in line and `blocked` no longer holds, then runs `op`.
+    // Whoever leaves the queue notifies `event` so the next waiter re-checks.
+    pub(crate) fn run<R>(
+        &self,
+        event: &Event,
+        priority: Priority,
+        deadline: Option<&Deadline>,
+        blocked: impl Fn() -> bool,
+        op: impl FnOnce() -> R,
+    ) -> Result<R, PortError> {
+        let index = self.register(priority)?;
+
+        let waited = event.wait_while(
+            || {
+                !(self.is_first(index)
+                    && !blocked()
+                    && self
+                        .active
+                        .compare_exchange(0, current_pid(), Ordering::Acquire, Ordering::Relaxed)
+                        .is_ok())
+            },
+            deadline,
+        );
+
+        let result = waited.map(|()| {
+            let result = op();
+            self.active.store(0, Ordering::Release);
+            result
+        });
+
+        self.unregister(index);
+        event.notify();
+        result
+    }
+
+    // Runs `op` holding the turn without queueing, spinning while another
+    // caller holds it; operations hold it only for a copy. A turn held by a
+    // process that died is taken over. The caller notifies the event the
+    // waiters sleep on afterwards, since one may have found the turn taken.
+    pub(crate) fn exclusive<R>(&self, op: impl FnOnce() -> R) -> R {
+        let pid = current_pid();
+        let mut spins = 0u32;
+        while let Err(holder) =
+            self.active
+                .compare_exchange_weak(0, pid, Ordering::Acquire, Ordering::Relaxed)
+        {
+            spins = spins.wrapping_add(1);
+            if spins.is_multiple_of(LIVENESS_SPINS) && holder != 0 && !process_alive(holder) {
+                let _ =
+                    self.active
+                        .compare_exchange(holder, 0, Ordering::AcqRel, Ordering::Relaxed);
+            }
+            spin_loop();
+        }
+
+        let result = op();
+        self.active.store(0, Ordering::Release);
+        result
+    }
+}
+
+// === Tests ===
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::futex::deadline_after;
+    use core::time::Duration;
+
+    #[test]
+    fn test_wait_queue_order() {
+        let queue = WaitQueue::new();
+        let first = queue.register(1).unwrap();
+        let urgent = queue.register(9).unwrap();
+
+        assert!(queue.is_first(first));
+        assert!(!queue.is_first(urgent));
+
+        queue.set_discipline(QueuingDiscipline::Priority);
+        assert!(!queue.is_first(first));
+        assert!(queue.is_first(urgent));
+        assert_eq!(queue.len(), 2);
+    }
+
+    #[test]
+    fn test_wait_queue_times_out() {
+        let queue = WaitQueue::new();
+        let event = Event::new();
+        let deadline = deadline_after(Duration::from_millis(5));
+
+        assert_eq!(
+            queue.run(&event, 0, Some(&deadline), || true, || ()),
+            Err(PortError::Timeout)
+        );
+        assert_eq!(queue.len(), 0);
+        assert_eq!(queue.run(&event, 0, None, || false, || 7), Ok(7));
+    }
+}
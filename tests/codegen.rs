@@ -0,0 +1,28 @@
+use std::env;
+use std::path::Path;
+use std::process::Command;
+
+// Builds a crate whose build script generates its ports module from a
+// configuration full of awkward names, so the generated code must compile.
+#[test]
+fn test_generated_ports_compile() {
+    let manifest = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/codegen/Cargo.toml");
+    let output = Command::new(env::var("CARGO").unwrap_or_else(|_| "cargo".into()))
+        .arg("check")
+        .arg("--quiet")
+        .arg("--manifest-path")
+        .arg(&manifest)
+        .env(
+            "CARGO_TARGET_DIR",
+            Path::new(env!("CARGO_TARGET_TMPDIR")).join("codegen"),
+        )
+        .env("RUSTFLAGS", "-D warnings")
+        .output()
+        .unwrap();
+
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+}
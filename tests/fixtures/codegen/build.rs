@@ -0,0 +1,3 @@
+fn main() {
+    master_project_queuing_port::codegen::write_ports("system.toml", "ports.rs").unwrap();
+}
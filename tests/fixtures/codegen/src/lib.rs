@@ -0,0 +1,16 @@
+mod ports {
+    include!(concat!(env!("OUT_DIR"), "/ports.rs"));
+}
+
+use master_project_queuing_port::{
+    DestinationPort, Message, MulticastSourcePort, PortError, SourcePort,
+};
+
+pub fn open_all() -> Result<(), PortError> {
+    let _: SourcePort<Message<16>, 4> = ports::self_::r#box::open()?;
+    let _: MulticastSourcePort = ports::self_::r#type::open()?;
+    let _: DestinationPort<Message<16>, 4> = ports::super_::__::open()?;
+    let _: DestinationPort<Message<16>, 4> = ports::super_::_1st_in::open()?;
+    let _: DestinationPort<Message<16>, 4> = ports::super_::Self_::open()?;
+    Ok(())
+}
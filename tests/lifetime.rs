@@ -0,0 +1,99 @@
+use std::panic::{self, AssertUnwindSafe};
+
+use master_project_queuing_port::{
+    release_shared, unlink, Lifetime, PortError, PortHandle, QueuingPort,
+};
+
+type Counter = PortHandle<u32>;
+
+// Runs `f` in a child process and waits for it. The child exits without
+// running destructors, as a crashed process would.
+fn in_child(f: impl FnOnce()) {
+    match unsafe { libc::fork() } {
+        0 => {
+            let code = match panic::catch_unwind(AssertUnwindSafe(f)) {
+                Ok(()) => 0,
+                Err(_) => 1,
+            };
+            unsafe { libc::_exit(code) };
+        }
+        child => {
+            let mut status = 0;
+            unsafe { libc::waitpid(child, &mut status, 0) };
+            assert!(libc::WIFEXITED(status) && libc::WEXITSTATUS(status) == 0);
+        }
+    }
+}
+
+#[test]
+fn test_owned_segment_is_removed_on_drop() {
+    let os_id = "test_lifetime_owned";
+    let _ = unlink(os_id);
+
+    let port = Counter::create(os_id).unwrap();
+    assert_eq!(port.segment().lifetime(), Lifetime::Owned);
+    port.enqueue(1).unwrap();
+    drop(port);
+
+    assert_eq!(Counter::open(os_id).err(), Some(PortError::ShmOpen));
+}
+
+#[test]
+fn test_persistent_segment_outlives_creator() {
+    let os_id = "test_lifetime_persistent";
+    let _ = unlink(os_id);
+
+    let mut port = Counter::create(os_id).unwrap();
+    port.segment_mut().set_lifetime(Lifetime::Persistent);
+    port.enqueue(7).unwrap();
+    drop(port);
+
+    // The segment and its messages stay, and it cannot be created again.
+    assert_eq!(Counter::create(os_id).err(), Some(PortError::AlreadyExists));
+    let port = Counter::open(os_id).unwrap();
+    assert_eq!(port.dequeue(), Ok(7));
+    port.enqueue(8).unwrap();
+    drop(port);
+
+    // Unlinking clears it, and the next create starts from an empty port.
+    unlink(os_id).unwrap();
+    assert_eq!(unlink(os_id), Err(PortError::ShmOpen));
+    let port = Counter::create(os_id).unwrap();
+    assert_eq!(port.dequeue(), Err(PortError::Empty));
+}
+
+#[test]
+fn test_rerun_starts_clean() {
+    let os_id = "test_lifetime_rerun";
+    let _ = unlink(os_id);
+
+    // A run that releases its ports leaves nothing behind.
+    in_child(|| {
+        QueuingPort::<u32>::enqueue_shared(1, os_id).unwrap();
+        assert!(release_shared(os_id));
+    });
+    assert_eq!(Counter::open(os_id).err(), Some(PortError::ShmOpen));
+
+    // A run that crashes leaves its segment and messages behind...
+    in_child(|| {
+        QueuingPort::<u32>::enqueue_shared(2, os_id).unwrap();
+    });
+    assert!(Counter::open(os_id).is_ok());
+
+    // ...until the next run unlinks it before starting.
+    unlink(os_id).unwrap();
+    QueuingPort::<u32>::enqueue_shared(3, os_id).unwrap();
+    assert_eq!(QueuingPort::<u32>::dequeue_shared(os_id), Ok(3));
+    assert_eq!(
+        QueuingPort::<u32>::dequeue_shared(os_id),
+        Err(PortError::Empty)
+    );
+    assert!(release_shared(os_id));
+    assert_eq!(Counter::open(os_id).err(), Some(PortError::ShmOpen));
+
+    // Unlinking a segment this process created through the registry.
+    QueuingPort::<u32>::enqueue_shared(4, os_id).unwrap();
+    assert_eq!(unlink(os_id), Ok(()));
+    assert_eq!(Counter::open(os_id).err(), Some(PortError::ShmOpen));
+    assert_eq!(unlink(os_id), Err(PortError::ShmOpen));
+}